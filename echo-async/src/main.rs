@@ -13,7 +13,6 @@ use tokio_uring_reactor::{
 	},
 	Handle,
 };
-use crate::timeout::{TryFutureExt as _};
 
 async fn handle_connection(handle: Handle, c: tokio_uring_reactor::net::TcpStream, a: net::SocketAddr) -> io::Result<()> {
 	println!("Connection from {}", a);
@@ -26,7 +25,7 @@ async fn handle_connection(handle: Handle, c: tokio_uring_reactor::net::TcpStrea
 		let con = connection.take().expect("connection missing");
 		buf.resize_with(512, Default::default);
 
-		let (n, mut buf, con) = await!(con.read(&handle, buf).timeout(Duration::from_secs(3)))?;
+		let (n, mut buf, con) = await!(con.read_timeout(&handle, buf, Duration::from_secs(3)))?;
 		if n == 0 {
 			println!("Connection from {} closing", a);
 			return Ok(())
@@ -28,6 +28,16 @@ static_assert!(
 	size_of::<SubmissionEntryOperationFlags>() == 4,
 	size_of::<SubmissionEntryExtraData>() == 24,
 	size_of::<CompletionEntry>() == 16,
+	size_of::<OpenHow>() == 24,
+	size_of::<KernelTimespec>() == 16,
+	size_of::<ProbeHeader>() == 16,
+	size_of::<GeteventsArg>() == 24,
+	size_of::<FilesUpdate>() == 16,
+	size_of::<ResourceRegister>() == 32,
+	size_of::<FileIndexRange>() == 16,
+	size_of::<ProbeOperation>() == 8,
+	size_of::<BufferRingRegister>() == 40,
+	size_of::<BufferRingEntry>() == 16,
 	true
 );
 
@@ -47,6 +57,10 @@ pub unsafe fn io_uring_setup(entries: u32, params: *mut SetupParameters) -> c_in
 	) as c_int
 }
 
+/// size of the kernel signal mask (`_NSIG / 8`); the kernel rejects
+/// other sizes (`libc::sigset_t` is larger)
+pub const KERNEL_SIGSET_SIZE: usize = 8;
+
 pub unsafe fn io_uring_enter(fd: c_int, to_submit: c_uint, min_complete: c_uint, flags: c_uint, sig: *const libc::sigset_t) -> c_int {
 	libc::syscall(
 		syscalls::SYS_io_uring_enter,
@@ -55,7 +69,21 @@ pub unsafe fn io_uring_enter(fd: c_int, to_submit: c_uint, min_complete: c_uint,
 		min_complete as c_long,
 		flags as c_long,
 		sig as usize as c_long,
-		core::mem::size_of::<libc::sigset_t>() as c_long,
+		KERNEL_SIGSET_SIZE as c_long,
+	) as c_int
+}
+
+/// io_uring_enter with a custom argument (`EnterFlags::EXT_ARG` expects
+/// a `GeteventsArg`)
+pub unsafe fn io_uring_enter2(fd: c_int, to_submit: c_uint, min_complete: c_uint, flags: c_uint, arg: *const c_void, argsz: usize) -> c_int {
+	libc::syscall(
+		syscalls::SYS_io_uring_enter,
+		fd as c_long,
+		to_submit as c_long,
+		min_complete as c_long,
+		flags as c_long,
+		arg as usize as c_long,
+		argsz as c_long,
 	) as c_int
 }
 
@@ -76,6 +104,12 @@ bitflags! {
 		const GETEVENTS = (1 << 0);
 		/// `IORING_ENTER_SQ_WAKEUP`
 		const SQ_WAKEUP = (1 << 1);
+		/// `IORING_ENTER_SQ_WAIT`: wait for free space in the
+		/// submission queue (SQPOLL)
+		const SQ_WAIT = (1 << 2);
+		/// `IORING_ENTER_EXT_ARG`: the signal mask argument is a
+		/// `GeteventsArg` (`Features::EXT_ARG`)
+		const EXT_ARG = (1 << 3);
 
 		// don't truncate any bits
 		#[doc(hidden)]
@@ -83,6 +117,24 @@ bitflags! {
 	}
 }
 
+/// Argument for io_uring_enter with `EnterFlags::EXT_ARG`
+///
+/// C: `struct io_uring_getevents_arg`
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct GeteventsArg {
+	/// pointer to `libc::sigset_t` (or 0)
+	pub sigmask: u64,
+	/// size of the signal mask (`KERNEL_SIGSET_SIZE`)
+	pub sigmask_sz: u32,
+	/// minimum wait time in microseconds before returning with less
+	/// than `min_complete` completions (`Features::MIN_TIMEOUT`)
+	pub min_wait_usec: u32,
+	/// pointer to `KernelTimespec` (or 0): relative timeout for
+	/// waiting
+	pub ts: u64,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct RegisterOpCode(pub u32);
 
@@ -95,6 +147,168 @@ impl RegisterOpCode {
 	pub const REGISTER_FILES: Self = Self(2);
 	/// `IORING_UNREGISTER_FILES`
 	pub const UNREGISTER_FILES: Self = Self(3);
+	/// `IORING_REGISTER_FILES_UPDATE`: replace registered fds
+	/// (`FilesUpdate`)
+	pub const REGISTER_FILES_UPDATE: Self = Self(6);
+	/// `IORING_REGISTER_PROBE`
+	pub const REGISTER_PROBE: Self = Self(8);
+	/// `IORING_REGISTER_FILES2`: register fds with tags or sparse
+	/// (`ResourceRegister`)
+	pub const REGISTER_FILES2: Self = Self(13);
+	/// `IORING_REGISTER_PBUF_RING`: register a ring of provided buffers
+	/// (`BufferRingRegister`)
+	pub const REGISTER_PBUF_RING: Self = Self(22);
+	/// `IORING_UNREGISTER_PBUF_RING`: unregister a ring of provided
+	/// buffers (`BufferRingRegister`, only `bgid` is used)
+	pub const UNREGISTER_PBUF_RING: Self = Self(23);
+	/// `IORING_REGISTER_FILE_ALLOC_RANGE`: range for kernel allocated
+	/// fixed file slots (`FileIndexRange`)
+	pub const REGISTER_FILE_ALLOC_RANGE: Self = Self(25);
+}
+
+/// Argument for `RegisterOpCode::REGISTER_FILES_UPDATE`; `nr_args` is
+/// the number of fds.
+///
+/// C: `struct io_uring_files_update`
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FilesUpdate {
+	/// first slot to update
+	pub offset: u32,
+	_resv: u32,
+	/// pointer to `[i32; nr_args]`; -1 clears a slot, `SKIP` keeps it
+	pub fds: u64,
+}
+
+impl FilesUpdate {
+	/// `IORING_REGISTER_FILES_SKIP`: don't update the slot
+	pub const SKIP: i32 = -2;
+
+	/// `fds` needs to live until the update was registered
+	pub fn new(offset: u32, fds: &[i32]) -> Self {
+		FilesUpdate {
+			offset,
+			_resv: 0,
+			fds: fds.as_ptr() as usize as u64,
+		}
+	}
+}
+
+/// Argument for `RegisterOpCode::REGISTER_FILES2`; `nr_args` is the
+/// size of this struct.
+///
+/// C: `struct io_uring_rsrc_register`
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ResourceRegister {
+	/// number of resources (table size)
+	pub nr: u32,
+	pub flags: ResourceRegisterFlags,
+	_resv2: u64,
+	/// pointer to `[i32; nr]` (fds); ignored for sparse tables
+	pub data: u64,
+	/// pointer to `[u64; nr]` (or 0); for a non-zero tag the kernel
+	/// posts a completion with `user_data = tag` once the resource is
+	/// no longer used after removal
+	pub tags: u64,
+}
+
+impl ResourceRegister {
+	pub fn new(nr: u32, flags: ResourceRegisterFlags, data: u64, tags: u64) -> Self {
+		ResourceRegister {
+			nr,
+			flags,
+			_resv2: 0,
+			data,
+			tags,
+		}
+	}
+}
+
+bitflags! {
+	#[derive(Default)]
+	pub struct ResourceRegisterFlags: u32 {
+		/// `IORING_RSRC_REGISTER_SPARSE`: register `nr` empty slots
+		const SPARSE = (1 << 0);
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
+/// Argument for `RegisterOpCode::REGISTER_FILE_ALLOC_RANGE`
+///
+/// C: `struct io_uring_file_index_range`
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FileIndexRange {
+	pub off: u32,
+	pub len: u32,
+	_resv: u64,
+}
+
+impl FileIndexRange {
+	pub fn new(off: u32, len: u32) -> Self {
+		FileIndexRange {
+			off,
+			len,
+			_resv: 0,
+		}
+	}
+}
+
+/// Argument for `RegisterOpCode::REGISTER_PBUF_RING` and
+/// `RegisterOpCode::UNREGISTER_PBUF_RING`; `nr_args` is 1.
+///
+/// C: `struct io_uring_buf_reg`
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct BufferRingRegister {
+	/// pointer to `[BufferRingEntry; ring_entries]`; needs to be page
+	/// aligned
+	pub ring_addr: u64,
+	/// number of entries; needs to be a power of 2 (max 32768)
+	pub ring_entries: u32,
+	/// buffer group id; operations select buffers from it with
+	/// `SubmissionEntryFlags::BUFFER_SELECT`
+	pub bgid: u16,
+	pub flags: u16,
+	_resv: [u64; 3],
+}
+
+impl BufferRingRegister {
+	pub fn new(ring_addr: u64, ring_entries: u32, bgid: u16) -> Self {
+		BufferRingRegister {
+			ring_addr,
+			ring_entries,
+			bgid,
+			flags: 0,
+			_resv: [0; 3],
+		}
+	}
+}
+
+/// Entry in a ring of provided buffers
+///
+/// The reserved field of the first entry is the ring tail, which is
+/// written by the application (see `TAIL_OFFSET`); the kernel keeps the
+/// head internally.
+///
+/// C: `struct io_uring_buf`
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct BufferRingEntry {
+	pub addr: u64,
+	pub len: u32,
+	/// buffer id; reported in `CompletionFlags::buffer_id`
+	pub bid: u16,
+	_resv: u16,
+}
+
+impl BufferRingEntry {
+	/// byte offset of the (u16) ring tail from the start of the ring
+	pub const TAIL_OFFSET: usize = 14;
 }
 
 /// Passed in for io_uring_setup(2). Copied back with updated info on
@@ -121,8 +335,13 @@ pub struct SetupParameters {
 	/// (input) used if SQPOLL flag is active; timeout in milliseconds
 	/// until kernel poll thread goes to sleep.
 	pub sq_thread_idle: u32,
+	/// (output) features supported by the kernel
+	pub features: Features,
+	/// (input) used if ATTACH_WQ flag is active: fd of an existing
+	/// io_uring instance to share the async worker thread pool with
+	pub wq_fd: u32,
 	// reserved
-	_reserved: [u32; 5],
+	_reserved: [u32; 3],
 	/// (output) submission queue ring data field offsets
 	pub sq_off: SubmissionQueueRingOffsets,
 	/// (output) completion queue ring data field offsets
@@ -159,6 +378,62 @@ bitflags! {
 		/// `IORING_SETUP_SQ_AFF`: sq_thread_cpu is valid
 		const SQ_AFF = (1 << 2);
 
+		/// `IORING_SETUP_CQSIZE`: app defines CQ size
+		/// (`SetupParameters.cq_entries` is an input)
+		const CQSIZE = (1 << 3);
+
+		/// `IORING_SETUP_CLAMP`: clamp SQ/CQ ring sizes to the maximum
+		/// instead of failing
+		const CLAMP = (1 << 4);
+
+		/// `IORING_SETUP_ATTACH_WQ`: attach to existing async worker
+		/// pool (`SetupParameters.wq_fd`)
+		const ATTACH_WQ = (1 << 5);
+
+		/// `IORING_SETUP_R_DISABLED`: start with ring disabled (enable
+		/// with `IORING_REGISTER_ENABLE_RINGS`)
+		const R_DISABLED = (1 << 6);
+
+		/// `IORING_SETUP_SUBMIT_ALL`: continue submit on error
+		const SUBMIT_ALL = (1 << 7);
+
+		/// `IORING_SETUP_COOP_TASKRUN`: don't interrupt running tasks
+		/// to run completion task work; it is run on the next
+		/// transition into the kernel instead
+		const COOP_TASKRUN = (1 << 8);
+
+		/// `IORING_SETUP_TASKRUN_FLAG`: set `IORING_SQ_TASKRUN` in the
+		/// SQ ring flags if task work is pending (requires
+		/// `COOP_TASKRUN` or `DEFER_TASKRUN`)
+		const TASKRUN_FLAG = (1 << 9);
+
+		/// `IORING_SETUP_SQE128`: SQEs are 128 bytes
+		const SQE128 = (1 << 10);
+
+		/// `IORING_SETUP_CQE32`: CQEs are 32 bytes
+		const CQE32 = (1 << 11);
+
+		/// `IORING_SETUP_SINGLE_ISSUER`: only one task is allowed to
+		/// submit requests
+		const SINGLE_ISSUER = (1 << 12);
+
+		/// `IORING_SETUP_DEFER_TASKRUN`: defer running task work to
+		/// get events (io_uring_enter with `GETEVENTS`); requires
+		/// `SINGLE_ISSUER`
+		const DEFER_TASKRUN = (1 << 13);
+
+		/// `IORING_SETUP_NO_MMAP`: application provides the memory for
+		/// the rings
+		const NO_MMAP = (1 << 14);
+
+		/// `IORING_SETUP_REGISTERED_FD_ONLY`: only return a registered
+		/// ring index instead of a fd (requires `NO_MMAP`)
+		const REGISTERED_FD_ONLY = (1 << 15);
+
+		/// `IORING_SETUP_NO_SQARRAY`: no SQ index array, SQ ring
+		/// indices point directly into the SQE array
+		const NO_SQARRAY = (1 << 16);
+
 		// don't truncate any bits
 		#[doc(hidden)]
 		const _ALL = !0;
@@ -208,6 +483,89 @@ pub struct SubmissionQueueRingOffsets {
 	_reserved: [u32; 3],
 }
 
+bitflags! {
+	/// io_uring_setup() output features
+	#[derive(Default)]
+	pub struct Features: u32 {
+		/// `IORING_FEAT_SINGLE_MMAP`: submission and completion queue
+		/// rings can be mapped with a single mmap (at
+		/// `SUBMISSION_QUEUE_RING_OFFSET`)
+		const SINGLE_MMAP = (1 << 0);
+
+		/// `IORING_FEAT_NODROP`: completions aren't dropped if the
+		/// completion queue is full; they are kept in an internal
+		/// overflow list instead
+		const NODROP = (1 << 1);
+
+		/// `IORING_FEAT_SUBMIT_STABLE`: data for submitted entries is
+		/// consumed on submission (the application doesn't need to keep
+		/// e.g. iovecs alive until completion)
+		const SUBMIT_STABLE = (1 << 2);
+
+		/// `IORING_FEAT_RW_CUR_POS`: offset `!0` for read/write uses
+		/// (and updates) the current file position
+		const RW_CUR_POS = (1 << 3);
+
+		/// `IORING_FEAT_CUR_PERSONALITY`: requests use the credentials
+		/// of the submitting task (instead of the ring creator)
+		const CUR_PERSONALITY = (1 << 4);
+
+		/// `IORING_FEAT_FAST_POLL`: operations on non-blocking files
+		/// are retried internally using poll instead of a worker thread
+		const FAST_POLL = (1 << 5);
+
+		/// `IORING_FEAT_POLL_32BITS`: poll events use 32 bits
+		const POLL_32BITS = (1 << 6);
+
+		/// `IORING_FEAT_SQPOLL_NONFIXED`: SQPOLL doesn't require fixed
+		/// files
+		const SQPOLL_NONFIXED = (1 << 7);
+
+		/// `IORING_FEAT_EXT_ARG`: io_uring_enter supports
+		/// `IORING_ENTER_EXT_ARG`
+		const EXT_ARG = (1 << 8);
+
+		/// `IORING_FEAT_NATIVE_WORKERS`: async workers are threads of
+		/// the submitting task
+		const NATIVE_WORKERS = (1 << 9);
+
+		/// `IORING_FEAT_RSRC_TAGS`: registered resources support tags
+		const RSRC_TAGS = (1 << 10);
+
+		/// `IORING_FEAT_CQE_SKIP`: supports
+		/// `SubmissionEntryFlags::CQE_SKIP_SUCCESS`
+		const CQE_SKIP = (1 << 11);
+
+		/// `IORING_FEAT_LINKED_FILE`: files of linked requests are
+		/// resolved when the request is started (not on submission)
+		const LINKED_FILE = (1 << 12);
+
+		/// `IORING_FEAT_REG_REG_RING`: the ring fd can be registered
+		/// with itself
+		const REG_REG_RING = (1 << 13);
+
+		/// `IORING_FEAT_RECVSEND_BUNDLE`: send/recv can use bundles of
+		/// provided buffers
+		const RECVSEND_BUNDLE = (1 << 14);
+
+		/// `IORING_FEAT_MIN_TIMEOUT`: io_uring_enter supports a minimum
+		/// wait timeout
+		const MIN_TIMEOUT = (1 << 15);
+
+		/// `IORING_FEAT_RW_ATTR`: read/write support attributes (e.g.
+		/// protection information)
+		const RW_ATTR = (1 << 16);
+
+		/// `IORING_FEAT_NO_IOWAIT`: waiting can be excluded from iowait
+		/// accounting
+		const NO_IOWAIT = (1 << 17);
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
 bitflags! {
 	#[derive(Default)]
 	pub struct SubmissionQueueFlags: u32 {
@@ -217,6 +575,15 @@ bitflags! {
 		/// on wakeup
 		const NEED_WAKEUP = (1 << 0);
 
+		/// `IORING_SQ_CQ_OVERFLOW`: completion queue overflowed; kernel
+		/// keeps the completions (`Features::NODROP`) until they are
+		/// flushed by io_uring_enter with `EnterFlags::GETEVENTS`
+		const CQ_OVERFLOW = (1 << 1);
+
+		/// `IORING_SQ_TASKRUN`: task work pending, needs io_uring_enter
+		/// (`SetupFlags::TASKRUN_FLAG`)
+		const TASKRUN = (1 << 2);
+
 		// don't truncate any bits
 		#[doc(hidden)]
 		const _ALL = !0;
@@ -291,6 +658,35 @@ pub enum Operation {
 	WriteFixed = 5,
 	PollAdd = 6,
 	PollRemove = 7,
+	SyncFileRange = 8,
+	SendMsg = 9,
+	RecvMsg = 10,
+	Timeout = 11,
+	TimeoutRemove = 12,
+	Accept = 13,
+	AsyncCancel = 14,
+	LinkTimeout = 15,
+	Connect = 16,
+	Fallocate = 17,
+	OpenAt = 18,
+	Close = 19,
+	Statx = 21,
+	Fadvise = 24,
+	Madvise = 25,
+	Send = 26,
+	Recv = 27,
+	OpenAt2 = 28,
+	Splice = 30,
+	ProvideBuffers = 31,
+	RemoveBuffers = 32,
+	Tee = 33,
+	Shutdown = 34,
+	RenameAt = 35,
+	UnlinkAt = 36,
+	MkdirAt = 37,
+	SymlinkAt = 38,
+	LinkAt = 39,
+	Ftruncate = 55,
 }
 
 impl Default for Operation {
@@ -313,6 +709,35 @@ impl RawOperation {
 			5 => Operation::WriteFixed,
 			6 => Operation::PollAdd,
 			7 => Operation::PollRemove,
+			8 => Operation::SyncFileRange,
+			9 => Operation::SendMsg,
+			10 => Operation::RecvMsg,
+			11 => Operation::Timeout,
+			12 => Operation::TimeoutRemove,
+			13 => Operation::Accept,
+			14 => Operation::AsyncCancel,
+			15 => Operation::LinkTimeout,
+			16 => Operation::Connect,
+			17 => Operation::Fallocate,
+			18 => Operation::OpenAt,
+			19 => Operation::Close,
+			21 => Operation::Statx,
+			24 => Operation::Fadvise,
+			25 => Operation::Madvise,
+			26 => Operation::Send,
+			27 => Operation::Recv,
+			28 => Operation::OpenAt2,
+			30 => Operation::Splice,
+			31 => Operation::ProvideBuffers,
+			32 => Operation::RemoveBuffers,
+			33 => Operation::Tee,
+			34 => Operation::Shutdown,
+			35 => Operation::RenameAt,
+			36 => Operation::UnlinkAt,
+			37 => Operation::MkdirAt,
+			38 => Operation::SymlinkAt,
+			39 => Operation::LinkAt,
+			55 => Operation::Ftruncate,
 			_ => return None,
 		})
 	}
@@ -342,6 +767,43 @@ bitflags! {
 		/// registered fileset (array of fds) instead.
 		const FIXED_FILE = (1 << 0);
 
+		/// IOSQE_IO_DRAIN: issue after inflight IO
+		///
+		/// The entry won't be started before all previously submitted
+		/// entries completed, and later entries won't be started before
+		/// this one completed.
+		const IO_DRAIN = (1 << 1);
+
+		/// IOSQE_IO_LINK: link next entry to this one
+		///
+		/// The next entry won't be started before this one completed
+		/// successfully; if this one fails the next one is canceled.
+		/// A `LinkTimeout` entry following this one cancels it instead
+		/// once the timeout expires.
+		const IO_LINK = (1 << 2);
+
+		/// IOSQE_IO_HARDLINK: like `IO_LINK`, but the chain isn't
+		/// broken if this entry fails
+		const IO_HARDLINK = (1 << 3);
+
+		/// IOSQE_ASYNC: always go async
+		///
+		/// Don't try to complete the operation inline (nonblocking)
+		/// first, always punt it to the async worker threads.
+		const ASYNC = (1 << 4);
+
+		/// IOSQE_BUFFER_SELECT: pick a buffer from the group in
+		/// `buf_index` when the operation needs it
+		///
+		/// `addr` isn't used for the data (readv still needs a single
+		/// iovec for the maximum length); the completion reports the
+		/// chosen buffer in `CompletionFlags::buffer_id`.
+		const BUFFER_SELECT = (1 << 5);
+
+		/// IOSQE_CQE_SKIP_SUCCESS: don't post a CQE if the request
+		/// succeeded
+		const CQE_SKIP_SUCCESS = (1 << 6);
+
 		// don't truncate any bits
 		#[doc(hidden)]
 		const _ALL = !0;
@@ -358,6 +820,14 @@ pub union SubmissionEntryOperationFlags {
 	pub rw_flags: ReadWriteFlags,
 	pub fsync_flags: FsyncFlags,
 	pub poll_events: PollFlags,
+	pub sync_range_flags: SyncFileRangeFlags,
+	pub timeout_flags: TimeoutFlags,
+	pub cancel_flags: CancelFlags,
+	pub msg_flags: MsgFlags,
+	pub accept_flags: AcceptFlags,
+	pub at_flags: AtFlags,
+	pub rename_flags: RenameFlags,
+	pub splice_flags: SpliceFlags,
 }
 
 impl fmt::Debug for SubmissionEntryOperationFlags {
@@ -367,6 +837,14 @@ impl fmt::Debug for SubmissionEntryOperationFlags {
 			.field("rw_flags", unsafe { &self.rw_flags })
 			.field("fsync_flags", unsafe { &self.fsync_flags })
 			.field("poll_events", unsafe { &self.poll_events })
+			.field("sync_range_flags", unsafe { &self.sync_range_flags })
+			.field("timeout_flags", unsafe { &self.timeout_flags })
+			.field("cancel_flags", unsafe { &self.cancel_flags })
+			.field("msg_flags", unsafe { &self.msg_flags })
+			.field("accept_flags", unsafe { &self.accept_flags })
+			.field("at_flags", unsafe { &self.at_flags })
+			.field("rename_flags", unsafe { &self.rename_flags })
+			.field("splice_flags", unsafe { &self.splice_flags })
 			.finish()
 	}
 }
@@ -395,6 +873,54 @@ impl From<PollFlags> for SubmissionEntryOperationFlags {
 	}
 }
 
+impl From<SyncFileRangeFlags> for SubmissionEntryOperationFlags {
+	fn from(sync_range_flags: SyncFileRangeFlags) -> Self {
+		Self { sync_range_flags }
+	}
+}
+
+impl From<TimeoutFlags> for SubmissionEntryOperationFlags {
+	fn from(timeout_flags: TimeoutFlags) -> Self {
+		Self { timeout_flags }
+	}
+}
+
+impl From<CancelFlags> for SubmissionEntryOperationFlags {
+	fn from(cancel_flags: CancelFlags) -> Self {
+		Self { cancel_flags }
+	}
+}
+
+impl From<MsgFlags> for SubmissionEntryOperationFlags {
+	fn from(msg_flags: MsgFlags) -> Self {
+		Self { msg_flags }
+	}
+}
+
+impl From<AcceptFlags> for SubmissionEntryOperationFlags {
+	fn from(accept_flags: AcceptFlags) -> Self {
+		Self { accept_flags }
+	}
+}
+
+impl From<AtFlags> for SubmissionEntryOperationFlags {
+	fn from(at_flags: AtFlags) -> Self {
+		Self { at_flags }
+	}
+}
+
+impl From<RenameFlags> for SubmissionEntryOperationFlags {
+	fn from(rename_flags: RenameFlags) -> Self {
+		Self { rename_flags }
+	}
+}
+
+impl From<SpliceFlags> for SubmissionEntryOperationFlags {
+	fn from(splice_flags: SpliceFlags) -> Self {
+		Self { splice_flags }
+	}
+}
+
 bitflags! {
 	#[derive(Default)]
 	pub struct ReadWriteFlags: u32 {
@@ -470,6 +996,115 @@ bitflags! {
 	}
 }
 
+bitflags! {
+	/// flags for sync_file_range(2)
+	#[derive(Default)]
+	pub struct SyncFileRangeFlags: u32 {
+		/// `SYNC_FILE_RANGE_WAIT_BEFORE`: wait upon write-out of all
+		/// pages in the range that have already been submitted before
+		/// performing any write
+		const WAIT_BEFORE = (1 << 0);
+		/// `SYNC_FILE_RANGE_WRITE`: initiate write-out of all dirty
+		/// pages in the range
+		const WRITE = (1 << 1);
+		/// `SYNC_FILE_RANGE_WAIT_AFTER`: wait upon write-out of all
+		/// pages in the range after performing any write
+		const WAIT_AFTER = (1 << 2);
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
+bitflags! {
+	/// `mode` for fallocate(2)
+	#[derive(Default)]
+	pub struct FallocateFlags: u32 {
+		/// `FALLOC_FL_KEEP_SIZE`: don't change file size
+		const KEEP_SIZE = 0x01;
+		/// `FALLOC_FL_PUNCH_HOLE`: deallocate range (requires
+		/// `KEEP_SIZE`)
+		const PUNCH_HOLE = 0x02;
+		#[doc(hidden)]
+		const NO_HIDE_STALE = 0x04;
+		/// `FALLOC_FL_COLLAPSE_RANGE`: remove range without leaving a
+		/// hole
+		const COLLAPSE_RANGE = 0x08;
+		/// `FALLOC_FL_ZERO_RANGE`: zero range (allocating it)
+		const ZERO_RANGE = 0x10;
+		/// `FALLOC_FL_INSERT_RANGE`: insert hole without overwriting
+		/// existing data
+		const INSERT_RANGE = 0x20;
+		/// `FALLOC_FL_UNSHARE_RANGE`: unshare shared blocks in range
+		const UNSHARE_RANGE = 0x40;
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
+/// `advice` for posix_fadvise(2)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct FadviseAdvice(pub u32);
+
+impl FadviseAdvice {
+	/// `POSIX_FADV_NORMAL`
+	pub const NORMAL: Self = Self(0);
+	/// `POSIX_FADV_RANDOM`
+	pub const RANDOM: Self = Self(1);
+	/// `POSIX_FADV_SEQUENTIAL`
+	pub const SEQUENTIAL: Self = Self(2);
+	/// `POSIX_FADV_WILLNEED`
+	pub const WILLNEED: Self = Self(3);
+	/// `POSIX_FADV_DONTNEED`
+	pub const DONTNEED: Self = Self(4);
+	/// `POSIX_FADV_NOREUSE`
+	pub const NOREUSE: Self = Self(5);
+}
+
+/// `advice` for madvise(2)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct MadviseAdvice(pub u32);
+
+impl MadviseAdvice {
+	/// `MADV_NORMAL`
+	pub const NORMAL: Self = Self(0);
+	/// `MADV_RANDOM`
+	pub const RANDOM: Self = Self(1);
+	/// `MADV_SEQUENTIAL`
+	pub const SEQUENTIAL: Self = Self(2);
+	/// `MADV_WILLNEED`
+	pub const WILLNEED: Self = Self(3);
+	/// `MADV_DONTNEED`
+	pub const DONTNEED: Self = Self(4);
+	/// `MADV_FREE`
+	pub const FREE: Self = Self(8);
+	/// `MADV_REMOVE`
+	pub const REMOVE: Self = Self(9);
+	/// `MADV_DONTFORK`
+	pub const DONTFORK: Self = Self(10);
+	/// `MADV_DOFORK`
+	pub const DOFORK: Self = Self(11);
+	/// `MADV_MERGEABLE`
+	pub const MERGEABLE: Self = Self(12);
+	/// `MADV_UNMERGEABLE`
+	pub const UNMERGEABLE: Self = Self(13);
+	/// `MADV_HUGEPAGE`
+	pub const HUGEPAGE: Self = Self(14);
+	/// `MADV_NOHUGEPAGE`
+	pub const NOHUGEPAGE: Self = Self(15);
+	/// `MADV_DONTDUMP`
+	pub const DONTDUMP: Self = Self(16);
+	/// `MADV_DODUMP`
+	pub const DODUMP: Self = Self(17);
+	/// `MADV_COLD`
+	pub const COLD: Self = Self(20);
+	/// `MADV_PAGEOUT`
+	pub const PAGEOUT: Self = Self(21);
+}
+
 bitflags! {
 	#[derive(Default)]
 	pub struct PollFlags: u16 {
@@ -492,11 +1127,286 @@ bitflags! {
 	}
 }
 
+bitflags! {
+	/// flags for poll add and poll update (remove) operations, passed
+	/// in the `len` field
+	#[derive(Default)]
+	pub struct PollAddFlags: u32 {
+		/// `IORING_POLL_ADD_MULTI`: post a completion for each event
+		/// (with `CompletionFlags::F_MORE` while the poll stays active)
+		const ADD_MULTI = (1 << 0);
+		/// `IORING_POLL_UPDATE_EVENTS`: update the event mask of an
+		/// existing poll (poll remove operation)
+		const UPDATE_EVENTS = (1 << 1);
+		/// `IORING_POLL_UPDATE_USER_DATA`: update the user_data of an
+		/// existing poll (poll remove operation)
+		const UPDATE_USER_DATA = (1 << 2);
+		/// `IORING_POLL_ADD_LEVEL`: level triggered poll
+		const ADD_LEVEL = (1 << 3);
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
+bitflags! {
+	/// flags for timeout operations
+	#[derive(Default)]
+	pub struct TimeoutFlags: u32 {
+		/// `IORING_TIMEOUT_ABS`: timespec is an absolute time (instead
+		/// of relative to submission)
+		const ABS = (1 << 0);
+		/// `IORING_TIMEOUT_UPDATE`: update timeout instead of removing
+		/// it (timeout remove operation)
+		const UPDATE = (1 << 1);
+		/// `IORING_TIMEOUT_BOOTTIME`: use `CLOCK_BOOTTIME` (default is
+		/// `CLOCK_MONOTONIC`)
+		const BOOTTIME = (1 << 2);
+		/// `IORING_TIMEOUT_REALTIME`: use `CLOCK_REALTIME` (default is
+		/// `CLOCK_MONOTONIC`)
+		const REALTIME = (1 << 3);
+		/// `IORING_LINK_TIMEOUT_UPDATE`: update linked timeout instead
+		/// of removing it (timeout remove operation)
+		const LINK_TIMEOUT_UPDATE = (1 << 4);
+		/// `IORING_TIMEOUT_ETIME_SUCCESS`: completion with `-ETIME`
+		/// doesn't break a link chain
+		const ETIME_SUCCESS = (1 << 5);
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
+bitflags! {
+	/// flags for async cancel operations
+	#[derive(Default)]
+	pub struct CancelFlags: u32 {
+		/// `IORING_ASYNC_CANCEL_ALL`: cancel all matching requests
+		/// instead of only the first one
+		const ALL = (1 << 0);
+		/// `IORING_ASYNC_CANCEL_FD`: match requests by fd instead of
+		/// `user_data`
+		const FD = (1 << 1);
+		/// `IORING_ASYNC_CANCEL_ANY`: match any request
+		const ANY = (1 << 2);
+		/// `IORING_ASYNC_CANCEL_FD_FIXED`: fd to match is an index into
+		/// the registered fileset
+		const FD_FIXED = (1 << 3);
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
+/// C: `struct __kernel_timespec`
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+#[repr(C)]
+pub struct KernelTimespec {
+	pub tv_sec: i64,
+	pub tv_nsec: i64,
+}
+
+impl From<core::time::Duration> for KernelTimespec {
+	fn from(d: core::time::Duration) -> Self {
+		KernelTimespec {
+			tv_sec: d.as_secs() as i64,
+			tv_nsec: d.subsec_nanos() as i64,
+		}
+	}
+}
+
+bitflags! {
+	/// flags for send(2), recv(2), sendmsg(2) and recvmsg(2)
+	#[derive(Default)]
+	pub struct MsgFlags: u32 {
+		const OOB = libc::MSG_OOB as u32;
+		const PEEK = libc::MSG_PEEK as u32;
+		const DONTROUTE = libc::MSG_DONTROUTE as u32;
+		const CTRUNC = libc::MSG_CTRUNC as u32;
+		const TRUNC = libc::MSG_TRUNC as u32;
+		const DONTWAIT = libc::MSG_DONTWAIT as u32;
+		const EOR = libc::MSG_EOR as u32;
+		const WAITALL = libc::MSG_WAITALL as u32;
+		const CONFIRM = libc::MSG_CONFIRM as u32;
+		const ERRQUEUE = libc::MSG_ERRQUEUE as u32;
+		const NOSIGNAL = libc::MSG_NOSIGNAL as u32;
+		const MORE = libc::MSG_MORE as u32;
+		const CMSG_CLOEXEC = libc::MSG_CMSG_CLOEXEC as u32;
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
+bitflags! {
+	/// flags for accept4(2)
+	#[derive(Default)]
+	pub struct AcceptFlags: u32 {
+		/// `SOCK_NONBLOCK`: set `O_NONBLOCK` on the accepted socket
+		const NONBLOCK = libc::SOCK_NONBLOCK as u32;
+		/// `SOCK_CLOEXEC`: set `FD_CLOEXEC` on the accepted socket
+		const CLOEXEC = libc::SOCK_CLOEXEC as u32;
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
+bitflags! {
+	/// accept flags passed in the `ioprio` field
+	#[derive(Default)]
+	pub struct AcceptIoPrioFlags: u16 {
+		/// `IORING_ACCEPT_MULTISHOT`: post a completion for each
+		/// accepted connection (with `CompletionFlags::F_MORE` while
+		/// the operation stays active)
+		const MULTISHOT = (1 << 0);
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
+bitflags! {
+	/// send/recv flags passed in the `ioprio` field
+	#[derive(Default)]
+	pub struct RecvSendIoPrioFlags: u16 {
+		/// `IORING_RECVSEND_POLL_FIRST`: wait for the socket to become
+		/// ready before trying the operation
+		const POLL_FIRST = (1 << 0);
+		/// `IORING_RECV_MULTISHOT`: post a completion for each received
+		/// chunk (with `CompletionFlags::F_MORE` while the operation
+		/// stays active); requires `SubmissionEntryFlags::BUFFER_SELECT`
+		const RECV_MULTISHOT = (1 << 1);
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
+bitflags! {
+	/// `AT_*` flags for the path based operations (statx, unlinkat,
+	/// linkat)
+	#[derive(Default)]
+	pub struct AtFlags: u32 {
+		/// `AT_SYMLINK_NOFOLLOW`: don't follow trailing symlink (statx)
+		const SYMLINK_NOFOLLOW = libc::AT_SYMLINK_NOFOLLOW as u32;
+		/// `AT_REMOVEDIR`: remove directory instead of file (unlinkat)
+		const REMOVEDIR = libc::AT_REMOVEDIR as u32;
+		/// `AT_SYMLINK_FOLLOW`: follow trailing symlink of old path (linkat)
+		const SYMLINK_FOLLOW = libc::AT_SYMLINK_FOLLOW as u32;
+		/// `AT_NO_AUTOMOUNT`: don't trigger automount (statx)
+		const NO_AUTOMOUNT = libc::AT_NO_AUTOMOUNT as u32;
+		/// `AT_EMPTY_PATH`: operate on the directory fd itself if path
+		/// is empty (statx, linkat)
+		const EMPTY_PATH = libc::AT_EMPTY_PATH as u32;
+		/// `AT_STATX_FORCE_SYNC`: force attribute sync with remote
+		/// filesystems (statx)
+		const STATX_FORCE_SYNC = 0x2000;
+		/// `AT_STATX_DONT_SYNC`: don't sync attributes with remote
+		/// filesystems (statx)
+		const STATX_DONT_SYNC = 0x4000;
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
+bitflags! {
+	/// flags for renameat2(2)
+	#[derive(Default)]
+	pub struct RenameFlags: u32 {
+		/// `RENAME_NOREPLACE`: don't overwrite target
+		const NOREPLACE = (1 << 0);
+		/// `RENAME_EXCHANGE`: atomically exchange old and new path
+		const EXCHANGE = (1 << 1);
+		/// `RENAME_WHITEOUT`: create whiteout object at old path
+		const WHITEOUT = (1 << 2);
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
+/// Argument for openat2(2)
+///
+/// C: `struct open_how`
+#[derive(Clone, Copy, Default, Debug)]
+#[repr(C)]
+pub struct OpenHow {
+	/// `O_*` flags
+	pub flags: u64,
+	/// file mode if a file is created
+	pub mode: u64,
+	pub resolve: ResolveFlags,
+}
+
+bitflags! {
+	/// path resolution flags for openat2(2)
+	#[derive(Default)]
+	pub struct ResolveFlags: u64 {
+		/// `RESOLVE_NO_XDEV`: block mount-point crossings
+		const NO_XDEV = 0x01;
+		/// `RESOLVE_NO_MAGICLINKS`: block traversal through procfs-style
+		/// "magic-links"
+		const NO_MAGICLINKS = 0x02;
+		/// `RESOLVE_NO_SYMLINKS`: block traversal through all symlinks
+		const NO_SYMLINKS = 0x04;
+		/// `RESOLVE_BENEATH`: block "lexical" trickery like "..",
+		/// symlinks and absolute paths which escape the directory
+		const BENEATH = 0x08;
+		/// `RESOLVE_IN_ROOT`: make all jumps to "/" and ".." be scoped
+		/// inside the directory
+		const IN_ROOT = 0x10;
+		/// `RESOLVE_CACHED`: only complete if resolution can be
+		/// completed through cached lookup
+		const CACHED = 0x20;
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
+bitflags! {
+	/// flags for splice(2) and tee(2)
+	#[derive(Default)]
+	pub struct SpliceFlags: u32 {
+		/// `SPLICE_F_MOVE`: attempt to move pages instead of copying
+		const MOVE = (1 << 0);
+		/// `SPLICE_F_NONBLOCK`: don't block on I/O
+		const NONBLOCK = (1 << 1);
+		/// `SPLICE_F_MORE`: expect more data
+		const MORE = (1 << 2);
+		/// `SPLICE_F_GIFT`: pages passed in are a gift (vmsplice only)
+		const GIFT = (1 << 3);
+		/// `SPLICE_F_FD_IN_FIXED`: the input fd is an index into the
+		/// registered fileset
+		///
+		/// io_uring specific; the output fd uses
+		/// `SubmissionEntryFlags::FIXED_FILE` instead.
+		const FD_IN_FIXED = (1 << 31);
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
 
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub union SubmissionEntryExtraData {
 	pub fixed: SubmissionEntryFixedOp,
+	pub file_index: SubmissionEntryFileIndexOp,
+	pub splice: SubmissionEntrySpliceOp,
 	_pad2: [u64; 3],
 }
 
@@ -504,6 +1414,8 @@ impl fmt::Debug for SubmissionEntryExtraData {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		f.debug_struct("SubmissionEntryExtraData")
 			.field("fixed", unsafe { &self.fixed })
+			.field("file_index", unsafe { &self.file_index })
+			.field("splice", unsafe { &self.splice })
 			.finish()
 	}
 }
@@ -511,15 +1423,150 @@ impl fmt::Debug for SubmissionEntryExtraData {
 #[derive(Clone, Copy, Default, Debug)]
 #[repr(C)]
 pub struct SubmissionEntryFixedOp {
-	/// index into fixed buffers
+	/// index into fixed buffers; buffer group id
+	/// (`SubmissionEntryFlags::BUFFER_SELECT`, provide and remove
+	/// buffers)
 	pub buf_index: u16,
 }
 
+#[derive(Clone, Copy, Default, Debug)]
+#[repr(C)]
+pub struct SubmissionEntryFileIndexOp {
+	_buf_index: u16,
+	_personality: u16,
+	/// fixed file slot + 1 the operation installs the new file into
+	/// (openat, openat2) or removes it from (close); 0 if not using a
+	/// fixed file slot.
+	pub file_index: u32,
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+#[repr(C)]
+pub struct SubmissionEntrySpliceOp {
+	_buf_index: u16,
+	_personality: u16,
+	/// input fd for splice and tee (index into registered fileset if
+	/// `SpliceFlags::FD_IN_FIXED` is set)
+	pub splice_fd_in: i32,
+}
+
 /// C: `struct io_uring_cqe`
 #[repr(C)]
 #[derive(Clone, Copy, Default, Debug)]
 pub struct CompletionEntry {
 	pub user_data: u64,
 	pub res: i32,
-	pub flags: u32,
+	pub flags: CompletionFlags,
+}
+
+impl CompletionEntry {
+	/// id of the selected buffer (`SubmissionEntryFlags::BUFFER_SELECT`)
+	pub fn buffer_id(&self) -> Option<u16> {
+		self.flags.buffer_id()
+	}
+
+	/// multishot request stays active and will generate more
+	/// completions
+	pub fn has_more(&self) -> bool {
+		self.flags.contains(CompletionFlags::F_MORE)
+	}
+}
+
+bitflags! {
+	/// completion entry flags; the upper 16 bits contain the buffer id
+	/// if `F_BUFFER` is set.
+	#[derive(Default)]
+	pub struct CompletionFlags: u32 {
+		/// `IORING_CQE_F_BUFFER`: upper 16 bits are the buffer id
+		const F_BUFFER = (1 << 0);
+		/// `IORING_CQE_F_MORE`: parent request will generate more
+		/// completions
+		const F_MORE = (1 << 1);
+		/// `IORING_CQE_F_SOCK_NONEMPTY`: more data to read after
+		/// socket recv
+		const F_SOCK_NONEMPTY = (1 << 2);
+		/// `IORING_CQE_F_NOTIF`: notification completion for zerocopy
+		/// send; the buffers can be reused
+		const F_NOTIF = (1 << 3);
+		/// `IORING_CQE_F_BUF_MORE`: provided buffer ring buffer was
+		/// only partially consumed and will be used for more
+		/// completions
+		const F_BUF_MORE = (1 << 4);
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
+impl CompletionFlags {
+	/// `IORING_CQE_BUFFER_SHIFT`
+	pub const BUFFER_SHIFT: u32 = 16;
+
+	/// id of the selected buffer if `F_BUFFER` is set
+	pub fn buffer_id(self) -> Option<u16> {
+		if self.contains(CompletionFlags::F_BUFFER) {
+			Some((self.bits() >> Self::BUFFER_SHIFT) as u16)
+		} else {
+			None
+		}
+	}
+}
+
+/// Header of the buffer passed to `RegisterOpCode::REGISTER_PROBE`;
+/// followed by `ProbeOperation` entries (as many as passed in
+/// `nr_args`).
+///
+/// The complete buffer must be zeroed before registering.
+///
+/// C: `struct io_uring_probe`
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ProbeHeader {
+	/// (output) last opcode supported by the kernel
+	pub last_op: RawOperation,
+	/// (output) number of filled `ProbeOperation` entries
+	pub ops_len: u8,
+	_resv: u16,
+	_resv2: [u32; 3],
+}
+
+/// C: `struct io_uring_probe_op`
+#[repr(C)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ProbeOperation {
+	pub op: RawOperation,
+	_resv: u8,
+	pub flags: ProbeOperationFlags,
+	_resv2: u32,
+}
+
+bitflags! {
+	#[derive(Default)]
+	pub struct ProbeOperationFlags: u16 {
+		/// `IO_URING_OP_SUPPORTED`
+		const SUPPORTED = (1 << 0);
+
+		// don't truncate any bits
+		#[doc(hidden)]
+		const _ALL = !0;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::CompletionFlags;
+
+	#[test]
+	fn buffer_id() {
+		assert_eq!(CompletionFlags::empty().buffer_id(), None);
+		assert_eq!(CompletionFlags::F_MORE.buffer_id(), None);
+		// buffer id bits without F_BUFFER are ignored
+		assert_eq!(CompletionFlags::from_bits_truncate(7 << CompletionFlags::BUFFER_SHIFT).buffer_id(), None);
+
+		let flags = CompletionFlags::F_BUFFER | CompletionFlags::F_MORE;
+		assert_eq!(CompletionFlags::from_bits_truncate(flags.bits() | (7 << CompletionFlags::BUFFER_SHIFT)).buffer_id(), Some(7));
+		assert_eq!(CompletionFlags::from_bits_truncate(flags.bits() | (0xffff << CompletionFlags::BUFFER_SHIFT)).buffer_id(), Some(0xffff));
+		assert_eq!(flags.buffer_id(), Some(0));
+	}
 }
@@ -2,6 +2,8 @@
 
 use crate::*;
 
+use core::ffi::CStr;
+
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 #[repr(u8)]
 pub enum IoPriorityLevel {
@@ -83,6 +85,33 @@ impl SubmissionEntry {
 		}
 	}
 
+	// reset everything but `user_data` and set opcode
+	fn prepare(&mut self, op: Operation) {
+		let user_data = self.user_data;
+		self.clear();
+		self.opcode = op.into();
+		self.user_data = user_data;
+	}
+
+	// kernel expects slot + 1; 0 means "no fixed file slot"
+	fn set_file_index(&mut self, file_index: Option<u32>) {
+		let file_index = match file_index {
+			Some(ndx) => ndx + 1,
+			None => 0,
+		};
+		self.extra.file_index.file_index = file_index;
+	}
+
+	fn set_fd(&mut self, fd: FileDescriptor) {
+		match fd {
+			FileDescriptor::FD(fd) => self.fd = fd,
+			FileDescriptor::Fixed(ndx) => {
+				self.flags |= SubmissionEntryFlags::FIXED_FILE;
+				self.fd = ndx as i32;
+			}
+		}
+	}
+
 	fn iov(&mut self, op: Operation, prio: IoPriority, fd: FileDescriptor, offset: u64, flags: ReadWriteFlags, iov: *const [libc::iovec]) {
 		self.opcode = op.into();
 		self.flags = Default::default();
@@ -170,24 +199,15 @@ impl SubmissionEntry {
 	}
 
 	// The CQE `res` will contain the mask with "ready" eventy flags
-	pub fn poll_add(&mut self, fd: FileDescriptor, flags: PollFlags) {
-		self.opcode = Operation::PollAdd.into();
-		self.flags = Default::default();
-		self.ioprio = EncodedIoPriority(0);
-		match fd {
-			FileDescriptor::FD(fd) => self.fd = fd,
-			FileDescriptor::Fixed(ndx) => {
-				self.flags |= SubmissionEntryFlags::FIXED_FILE;
-				self.fd = ndx as i32;
-			}
-		}
-		self.off = 0;
-		self.addr = 0;
-		self.len = 0;
-		self.op_flags = flags.into();
-		unsafe {
-			self.extra.fixed.buf_index = 0;
-		}
+	//
+	// With `PollAddFlags::ADD_MULTI` the poll stays active and posts a
+	// completion for each event (kernel 5.13+).
+	pub fn poll_add(&mut self, fd: FileDescriptor, flags: PollFlags, add_flags: PollAddFlags) {
+		self.prepare(Operation::PollAdd);
+		self.set_fd(fd);
+		self.len = add_flags.bits();
+		// kernel reads 32 bits (`poll32_events`)
+		self.op_flags = (flags.bits() as u32).into();
 	}
 
 	// the PollRemove operation will still complete (possibly with an empty mask)
@@ -204,4 +224,404 @@ impl SubmissionEntry {
 			self.extra.fixed.buf_index = 0;
 		}
 	}
+
+	// Update the event mask and/or user_data of an active poll in place
+	// (kernel 5.13+); the CQE `res` will be 0 or `-ENOENT` if the poll
+	// isn't active anymore.
+	//
+	// Updating the events of a multishot poll needs
+	// `PollAddFlags::ADD_MULTI` in `add_flags`, otherwise it becomes a
+	// single-shot poll.
+	pub fn poll_update(&mut self, match_user_data: u64, new_user_data: Option<u64>, events: Option<PollFlags>, add_flags: PollAddFlags) {
+		self.prepare(Operation::PollRemove);
+		self.addr = match_user_data;
+		let mut add_flags = add_flags & !(PollAddFlags::UPDATE_EVENTS | PollAddFlags::UPDATE_USER_DATA);
+		if let Some(new_user_data) = new_user_data {
+			add_flags |= PollAddFlags::UPDATE_USER_DATA;
+			// kernel calls this field `addr2`
+			self.off = new_user_data;
+		}
+		if let Some(events) = events {
+			add_flags |= PollAddFlags::UPDATE_EVENTS;
+			// kernel reads 32 bits (`poll32_events`)
+			self.op_flags = (events.bits() as u32).into();
+		}
+		self.len = add_flags.bits();
+	}
+
+	// msg (and all buffers it references) needs to live until operation is completed!
+	pub unsafe fn sendmsg(&mut self, fd: FileDescriptor, msg: *const libc::msghdr, flags: MsgFlags) {
+		self.prepare(Operation::SendMsg);
+		self.set_fd(fd);
+		self.addr = msg as usize as u64;
+		self.len = 1;
+		self.op_flags = flags.into();
+	}
+
+	// msg (and all buffers it references) needs to live until operation is completed!
+	pub unsafe fn recvmsg(&mut self, fd: FileDescriptor, msg: *mut libc::msghdr, flags: MsgFlags) {
+		self.prepare(Operation::RecvMsg);
+		self.set_fd(fd);
+		self.addr = msg as usize as u64;
+		self.len = 1;
+		self.op_flags = flags.into();
+	}
+
+	// buf needs to live until operation is completed!
+	pub unsafe fn send(&mut self, fd: FileDescriptor, buf: *const [u8], flags: MsgFlags) {
+		self.prepare(Operation::Send);
+		self.set_fd(fd);
+		let buf = &*buf;
+		self.addr = buf.as_ptr() as usize as u64;
+		self.len = buf.len() as u32;
+		self.op_flags = flags.into();
+	}
+
+	// buf needs to live until operation is completed!
+	pub unsafe fn recv(&mut self, fd: FileDescriptor, buf: *mut [u8], flags: MsgFlags) {
+		self.prepare(Operation::Recv);
+		self.set_fd(fd);
+		let buf = &mut *buf;
+		self.addr = buf.as_mut_ptr() as usize as u64;
+		self.len = buf.len() as u32;
+		self.op_flags = flags.into();
+	}
+
+	// Stays active and posts a completion for each received chunk, in a
+	// buffer picked from group `buf_group` (kernel 6.0+). The operation
+	// ends with a completion without `CompletionFlags::F_MORE`: on
+	// error, end of stream (`res` 0) or if no buffer was available
+	// (`ENOBUFS`).
+	pub fn recv_multishot(&mut self, fd: FileDescriptor, buf_group: u16, flags: MsgFlags) {
+		self.prepare(Operation::Recv);
+		self.set_fd(fd);
+		self.ioprio = EncodedIoPriority(RecvSendIoPrioFlags::RECV_MULTISHOT.bits());
+		self.op_flags = flags.into();
+		self.set_buffer_select(buf_group);
+	}
+
+	// The CQE `res` will contain the accepted fd.
+	//
+	// addr and addrlen can be null; otherwise both need to live until
+	// operation is completed, and addrlen must be initialized with the
+	// size of the storage addr points to (like for accept4(2)).
+	pub unsafe fn accept(&mut self, fd: FileDescriptor, addr: *mut libc::sockaddr, addrlen: *mut libc::socklen_t, flags: AcceptFlags) {
+		self.prepare(Operation::Accept);
+		self.set_fd(fd);
+		self.addr = addr as usize as u64;
+		// kernel calls this field `addr2`
+		self.off = addrlen as usize as u64;
+		self.op_flags = flags.into();
+	}
+
+	// Like `accept`, but stays active and posts a completion for each
+	// accepted connection (kernel 5.19+). The operation ends with a
+	// completion without `CompletionFlags::F_MORE` (on error or when the
+	// kernel can't continue it); the peer address isn't reported.
+	pub fn accept_multishot(&mut self, fd: FileDescriptor, flags: AcceptFlags) {
+		self.prepare(Operation::Accept);
+		self.set_fd(fd);
+		self.ioprio = EncodedIoPriority(AcceptIoPrioFlags::MULTISHOT.bits());
+		self.op_flags = flags.into();
+	}
+
+	// addr needs to live until operation is completed!
+	pub unsafe fn connect(&mut self, fd: FileDescriptor, addr: *const libc::sockaddr, addrlen: libc::socklen_t) {
+		self.prepare(Operation::Connect);
+		self.set_fd(fd);
+		self.addr = addr as usize as u64;
+		// kernel takes the length by value in `off`
+		self.off = addrlen as u64;
+	}
+
+	// how: one of `libc::SHUT_RD`, `libc::SHUT_WR` or `libc::SHUT_RDWR`
+	pub fn shutdown(&mut self, fd: FileDescriptor, how: libc::c_int) {
+		self.prepare(Operation::Shutdown);
+		self.set_fd(fd);
+		self.len = how as u32;
+	}
+
+	// The CQE `res` will contain the new fd, or 0 if `file_index` was
+	// given.
+	//
+	// `dir` is the directory fd `path` is relative to (or
+	// `libc::AT_FDCWD`); with `file_index` the new file is installed
+	// into that fixed file slot instead of the fd table.
+	//
+	// path needs to live until operation is completed!
+	pub unsafe fn openat(&mut self, dir: i32, path: &CStr, flags: libc::c_int, mode: libc::mode_t, file_index: Option<u32>) {
+		self.prepare(Operation::OpenAt);
+		self.fd = dir;
+		self.addr = path.as_ptr() as usize as u64;
+		self.len = mode as u32;
+		self.op_flags = (flags as u32).into();
+		self.set_file_index(file_index);
+	}
+
+	// Like `openat`, but takes an `OpenHow` (see openat2(2)).
+	//
+	// path and how need to live until operation is completed!
+	pub unsafe fn openat2(&mut self, dir: i32, path: &CStr, how: *const OpenHow, file_index: Option<u32>) {
+		self.prepare(Operation::OpenAt2);
+		self.fd = dir;
+		self.addr = path.as_ptr() as usize as u64;
+		// kernel calls this field `addr2`
+		self.off = how as usize as u64;
+		self.len = core::mem::size_of::<OpenHow>() as u32;
+		self.set_file_index(file_index);
+	}
+
+	// A `FileDescriptor::Fixed` slot is removed from the registered
+	// fileset (not looked up as fixed file).
+	pub fn close(&mut self, fd: FileDescriptor) {
+		self.prepare(Operation::Close);
+		match fd {
+			FileDescriptor::FD(fd) => self.fd = fd,
+			FileDescriptor::Fixed(ndx) => self.set_file_index(Some(ndx)),
+		}
+	}
+
+	// `mask` is a combination of `libc::STATX_*` flags.
+	//
+	// path and statxbuf need to live until operation is completed!
+	pub unsafe fn statx(&mut self, dir: i32, path: &CStr, flags: AtFlags, mask: u32, statxbuf: *mut libc::statx) {
+		self.prepare(Operation::Statx);
+		self.fd = dir;
+		self.addr = path.as_ptr() as usize as u64;
+		self.len = mask;
+		// kernel calls this field `addr2`
+		self.off = statxbuf as usize as u64;
+		self.op_flags = flags.into();
+	}
+
+	// paths need to live until operation is completed!
+	pub unsafe fn renameat(&mut self, old_dir: i32, old_path: &CStr, new_dir: i32, new_path: &CStr, flags: RenameFlags) {
+		self.prepare(Operation::RenameAt);
+		self.fd = old_dir;
+		self.addr = old_path.as_ptr() as usize as u64;
+		self.len = new_dir as u32;
+		// kernel calls this field `addr2`
+		self.off = new_path.as_ptr() as usize as u64;
+		self.op_flags = flags.into();
+	}
+
+	// only `AtFlags::REMOVEDIR` is supported.
+	//
+	// path needs to live until operation is completed!
+	pub unsafe fn unlinkat(&mut self, dir: i32, path: &CStr, flags: AtFlags) {
+		self.prepare(Operation::UnlinkAt);
+		self.fd = dir;
+		self.addr = path.as_ptr() as usize as u64;
+		self.op_flags = flags.into();
+	}
+
+	// path needs to live until operation is completed!
+	pub unsafe fn mkdirat(&mut self, dir: i32, path: &CStr, mode: libc::mode_t) {
+		self.prepare(Operation::MkdirAt);
+		self.fd = dir;
+		self.addr = path.as_ptr() as usize as u64;
+		self.len = mode as u32;
+	}
+
+	// creates symlink at `link_path` (relative to `new_dir`) pointing to
+	// `target`.
+	//
+	// paths need to live until operation is completed!
+	pub unsafe fn symlinkat(&mut self, target: &CStr, new_dir: i32, link_path: &CStr) {
+		self.prepare(Operation::SymlinkAt);
+		self.fd = new_dir;
+		self.addr = target.as_ptr() as usize as u64;
+		// kernel calls this field `addr2`
+		self.off = link_path.as_ptr() as usize as u64;
+	}
+
+	// only `AtFlags::SYMLINK_FOLLOW` and `AtFlags::EMPTY_PATH` are
+	// supported.
+	//
+	// paths need to live until operation is completed!
+	pub unsafe fn linkat(&mut self, old_dir: i32, old_path: &CStr, new_dir: i32, new_path: &CStr, flags: AtFlags) {
+		self.prepare(Operation::LinkAt);
+		self.fd = old_dir;
+		self.addr = old_path.as_ptr() as usize as u64;
+		self.len = new_dir as u32;
+		// kernel calls this field `addr2`
+		self.off = new_path.as_ptr() as usize as u64;
+		self.op_flags = flags.into();
+	}
+
+	// Select the data buffer from group `buf_group` when the operation
+	// needs it (readv, recv, ...); call after preparing the operation.
+	pub fn set_buffer_select(&mut self, buf_group: u16) {
+		self.flags |= SubmissionEntryFlags::BUFFER_SELECT;
+		// kernel calls this field `buf_group`
+		self.extra.fixed.buf_index = buf_group;
+	}
+
+	// Add `nbufs` buffers of `len` bytes each, starting at `addr`, to
+	// group `buf_group`; they get ids `bid`, `bid + 1`, ...
+	//
+	// The CQE `res` will contain the number of buffers added.
+	//
+	// buffers need to live until they were consumed (or removed)!
+	pub unsafe fn provide_buffers(&mut self, addr: *mut u8, len: u32, nbufs: u32, buf_group: u16, bid: u16) {
+		self.prepare(Operation::ProvideBuffers);
+		self.fd = nbufs as i32;
+		self.addr = addr as usize as u64;
+		self.len = len;
+		self.off = bid as u64;
+		// kernel calls this field `buf_group`
+		self.extra.fixed.buf_index = buf_group;
+	}
+
+	// The CQE `res` will contain the number of buffers removed.
+	pub fn remove_buffers(&mut self, nbufs: u32, buf_group: u16) {
+		self.prepare(Operation::RemoveBuffers);
+		self.fd = nbufs as i32;
+		// kernel calls this field `buf_group`
+		self.extra.fixed.buf_index = buf_group;
+	}
+
+	// if len == 0 it syncs until end of file
+	pub fn sync_file_range(&mut self, fd: FileDescriptor, offset: u64, len: u32, flags: SyncFileRangeFlags) {
+		self.prepare(Operation::SyncFileRange);
+		self.set_fd(fd);
+		self.off = offset;
+		self.len = len;
+		self.op_flags = flags.into();
+	}
+
+	pub fn fallocate(&mut self, fd: FileDescriptor, mode: FallocateFlags, offset: u64, len: u64) {
+		self.prepare(Operation::Fallocate);
+		self.set_fd(fd);
+		self.off = offset;
+		// kernel takes the length in `addr` and the mode in `len`
+		self.addr = len;
+		self.len = mode.bits();
+	}
+
+	// if len == 0 the advice applies until end of file
+	pub fn fadvise(&mut self, fd: FileDescriptor, offset: u64, len: u32, advice: FadviseAdvice) {
+		self.prepare(Operation::Fadvise);
+		self.set_fd(fd);
+		self.off = offset;
+		self.len = len;
+		self.op_flags = advice.0.into();
+	}
+
+	// addr must be page aligned (see madvise(2)); the advice applies to
+	// whatever is mapped there when the operation runs!
+	pub unsafe fn madvise(&mut self, addr: *const u8, len: u32, advice: MadviseAdvice) {
+		self.prepare(Operation::Madvise);
+		self.addr = addr as usize as u64;
+		self.len = len;
+		self.op_flags = advice.0.into();
+	}
+
+	pub fn ftruncate(&mut self, fd: FileDescriptor, len: u64) {
+		self.prepare(Operation::Ftruncate);
+		self.set_fd(fd);
+		self.off = len;
+	}
+
+	// offsets must be `None` for pipes; otherwise they are used instead
+	// of (and don't modify) the current file position.
+	//
+	// The CQE `res` will contain the number of bytes transferred.
+	pub fn splice(&mut self, fd_in: FileDescriptor, off_in: Option<u64>, fd_out: FileDescriptor, off_out: Option<u64>, len: u32, flags: SpliceFlags) {
+		self.prepare(Operation::Splice);
+		self.set_fd(fd_out);
+		self.off = off_out.unwrap_or(!0);
+		// kernel calls this field `splice_off_in`
+		self.addr = off_in.unwrap_or(!0);
+		self.len = len;
+		self.set_splice_fd_in(fd_in, flags);
+	}
+
+	// duplicates up to len bytes from pipe fd_in to pipe fd_out without
+	// consuming them.
+	//
+	// The CQE `res` will contain the number of bytes duplicated.
+	pub fn tee(&mut self, fd_in: FileDescriptor, fd_out: FileDescriptor, len: u32, flags: SpliceFlags) {
+		self.prepare(Operation::Tee);
+		self.set_fd(fd_out);
+		self.len = len;
+		self.set_splice_fd_in(fd_in, flags);
+	}
+
+	fn set_splice_fd_in(&mut self, fd_in: FileDescriptor, mut flags: SpliceFlags) {
+		flags.remove(SpliceFlags::FD_IN_FIXED);
+		let fd_in = match fd_in {
+			FileDescriptor::FD(fd) => fd,
+			FileDescriptor::Fixed(ndx) => {
+				flags |= SpliceFlags::FD_IN_FIXED;
+				ndx as i32
+			}
+		};
+		self.op_flags = flags.into();
+		self.extra.splice.splice_fd_in = fd_in;
+	}
+
+	// The CQE `res` will be `-ETIME` if the timeout expired, or 0 if
+	// `count` other requests completed first (`count == 0` never
+	// completes early).
+	//
+	// ts needs to live until the entry was submitted (the kernel copies
+	// it).
+	pub unsafe fn timeout(&mut self, ts: *const KernelTimespec, count: u32, flags: TimeoutFlags) {
+		self.prepare(Operation::Timeout);
+		self.addr = ts as usize as u64;
+		self.len = 1;
+		self.off = count as u64;
+		self.op_flags = flags.into();
+	}
+
+	// The removed timeout completes with `-ECANCELED`; the remove
+	// operation itself with `-ENOENT` if the timeout already completed.
+	pub fn timeout_remove(&mut self, match_user_data: u64) {
+		self.prepare(Operation::TimeoutRemove);
+		self.addr = match_user_data;
+	}
+
+	// Rearms a pending timeout with a new timespec; like
+	// `timeout_remove` completes with `-ENOENT` if the timeout already
+	// completed.
+	//
+	// ts needs to live until the entry was submitted (the kernel copies
+	// it).
+	pub unsafe fn timeout_update(&mut self, match_user_data: u64, ts: *const KernelTimespec, flags: TimeoutFlags) {
+		self.prepare(Operation::TimeoutRemove);
+		self.addr = match_user_data;
+		// kernel calls this field `addr2`
+		self.off = ts as usize as u64;
+		self.op_flags = (flags | TimeoutFlags::UPDATE).into();
+	}
+
+	// Must directly follow an entry with `SubmissionEntryFlags::IO_LINK`
+	// set; cancels that entry when the timeout expires.
+	//
+	// The CQE `res` will be `-ETIME` if the timeout expired (the linked
+	// entry then usually completes with `-ECANCELED`), or `-ECANCELED`
+	// if the linked entry completed first.
+	//
+	// ts needs to live until the entry was submitted (the kernel copies
+	// it).
+	pub unsafe fn link_timeout(&mut self, ts: *const KernelTimespec, flags: TimeoutFlags) {
+		self.prepare(Operation::LinkTimeout);
+		self.addr = ts as usize as u64;
+		self.len = 1;
+		self.op_flags = flags.into();
+	}
+
+	// The canceled operation completes with `-ECANCELED` (or `-EINTR`
+	// if it was already running); the cancel operation itself with 0,
+	// `-ENOENT` if no matching operation was found or `-EALREADY` if it
+	// was running and might still complete normally.
+	//
+	// With `CancelFlags::ALL` the CQE `res` of the cancel operation
+	// will contain the number of canceled operations.
+	pub fn async_cancel(&mut self, match_user_data: u64, flags: CancelFlags) {
+		self.prepare(Operation::AsyncCancel);
+		self.addr = match_user_data;
+		self.op_flags = flags.into();
+	}
 }
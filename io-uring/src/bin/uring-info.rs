@@ -0,0 +1,312 @@
+//! Print io_uring capabilities of the running kernel
+//!
+//! Usage: `uring-info [--json] [--entries N]`
+
+use std::fmt::Write as _;
+use std::io;
+use std::os::unix::io::{
+	AsRawFd,
+	RawFd,
+};
+
+use io_uring::{
+	Features,
+	RawOperation,
+	SetupParameters,
+	UringBuilder,
+	UringFile,
+};
+
+const FEATURES: &[(&str, Features)] = &[
+	("SINGLE_MMAP", Features::SINGLE_MMAP),
+	("NODROP", Features::NODROP),
+	("SUBMIT_STABLE", Features::SUBMIT_STABLE),
+	("RW_CUR_POS", Features::RW_CUR_POS),
+	("CUR_PERSONALITY", Features::CUR_PERSONALITY),
+	("FAST_POLL", Features::FAST_POLL),
+	("POLL_32BITS", Features::POLL_32BITS),
+	("SQPOLL_NONFIXED", Features::SQPOLL_NONFIXED),
+	("EXT_ARG", Features::EXT_ARG),
+	("NATIVE_WORKERS", Features::NATIVE_WORKERS),
+	("RSRC_TAGS", Features::RSRC_TAGS),
+	("CQE_SKIP", Features::CQE_SKIP),
+	("LINKED_FILE", Features::LINKED_FILE),
+	("REG_REG_RING", Features::REG_REG_RING),
+	("RECVSEND_BUNDLE", Features::RECVSEND_BUNDLE),
+	("MIN_TIMEOUT", Features::MIN_TIMEOUT),
+	("RW_ATTR", Features::RW_ATTR),
+	("NO_IOWAIT", Features::NO_IOWAIT),
+];
+
+// setup flag combinations to try; returns name and configured builder
+fn setup_flag_tests(entries: u32, wq_fd: RawFd) -> Vec<(&'static str, UringBuilder)> {
+	let mut tests = Vec::new();
+	let mut add = |name, f: &dyn Fn(&mut UringBuilder)| {
+		let mut builder = UringBuilder::new(entries);
+		f(&mut builder);
+		tests.push((name, builder));
+	};
+	add("IOPOLL", &|b| { b.iopoll(); });
+	add("SQPOLL", &|b| { b.sqpoll(1000); });
+	add("SQPOLL|SQ_AFF", &|b| { b.sqpoll(1000).sqpoll_cpu(0); });
+	add("CQSIZE", &|b| { b.cq_entries(4 * entries); });
+	add("CLAMP", &|b| { b.clamp(); });
+	add("ATTACH_WQ", &|b| { b.attach_wq_fd(wq_fd); });
+	add("R_DISABLED", &|b| { b.disabled(); });
+	add("SUBMIT_ALL", &|b| { b.submit_all(); });
+	add("COOP_TASKRUN", &|b| { b.coop_taskrun(false); });
+	add("COOP_TASKRUN|TASKRUN_FLAG", &|b| { b.coop_taskrun(true); });
+	add("SINGLE_ISSUER", &|b| { b.single_issuer(); });
+	add("SINGLE_ISSUER|DEFER_TASKRUN", &|b| { b.single_issuer().defer_taskrun(); });
+	tests
+}
+
+struct OpcodeInfo {
+	opcode: u8,
+	name: Option<String>,
+	supported: bool,
+}
+
+struct Report {
+	kernel: String,
+	entries: u32,
+	params: SetupParameters,
+	// (sq_entries, cq_entries) with CLAMP and maximum requested sizes
+	clamped: io::Result<(u32, u32)>,
+	setup_flags: Vec<(&'static str, io::Result<()>)>,
+	probe: io::Result<(RawOperation, Vec<OpcodeInfo>)>,
+}
+
+fn kernel_release() -> String {
+	let mut uts: libc::utsname = unsafe { std::mem::zeroed() };
+	if 0 != unsafe { libc::uname(&mut uts) } {
+		return String::from("unknown");
+	}
+	let release = unsafe { std::ffi::CStr::from_ptr(uts.release.as_ptr()) };
+	release.to_string_lossy().into_owned()
+}
+
+fn setup(entries: u32, builder: &UringBuilder) -> io::Result<(UringFile, SetupParameters)> {
+	builder.validate()?;
+	let mut params = *builder.params();
+	let file = UringFile::new(entries, &mut params)?;
+	Ok((file, params))
+}
+
+fn collect(entries: u32) -> io::Result<Report> {
+	let (_, params) = setup(entries, &UringBuilder::new(entries))?;
+	let mut uring = UringBuilder::new(entries).build()?;
+
+	let clamped = setup(u32::max_value(), UringBuilder::new(u32::max_value()).clamp().cq_entries(u32::max_value()))
+		.map(|(_, params)| (params.sq_entries, params.cq_entries));
+
+	let setup_flags = setup_flag_tests(entries, uring.file().as_raw_fd()).into_iter()
+		.map(|(name, builder)| (name, builder.build().map(|_| ())))
+		.collect();
+
+	let probe = uring.file().probe().map(|probe| {
+		let ops = (0..probe.operations().len()).map(|opcode| {
+			let op = RawOperation(opcode as u8);
+			OpcodeInfo {
+				opcode: op.0,
+				name: op.decode().map(|op| format!("{:?}", op)),
+				supported: probe.supports_raw(op),
+			}
+		}).collect();
+		(probe.last_op(), ops)
+	});
+
+	Ok(Report {
+		kernel: kernel_release(),
+		entries,
+		params,
+		clamped,
+		setup_flags,
+		probe,
+	})
+}
+
+fn opcode_name(op: &OpcodeInfo) -> String {
+	match op.name {
+		Some(ref name) => name.clone(),
+		None => format!("opcode {}", op.opcode),
+	}
+}
+
+fn print_text(report: &Report) {
+	let params = &report.params;
+	println!("kernel: {}", report.kernel);
+	println!();
+	println!("setup (entries = {}):", report.entries);
+	println!("  sq_entries: {}", params.sq_entries);
+	println!("  cq_entries: {}", params.cq_entries);
+	println!("  features: 0x{:x}", params.features.bits());
+	for &(name, feature) in FEATURES {
+		println!("    {:<16} {}", name, if params.features.contains(feature) { "yes" } else { "no" });
+	}
+	let unknown = params.features.bits() & !FEATURES.iter().fold(0, |all, &(_, f)| all | f.bits());
+	if 0 != unknown {
+		println!("    (unknown bits: 0x{:x})", unknown);
+	}
+	println!("  sq_off: {:?}", params.sq_off);
+	println!("  cq_off: {:?}", params.cq_off);
+	println!();
+	match report.clamped {
+		Ok((sq, cq)) => println!("maximum ring sizes (CLAMP): sq_entries = {}, cq_entries = {}", sq, cq),
+		Err(ref e) => println!("maximum ring sizes (CLAMP): failed: {}", e),
+	}
+	println!();
+	println!("setup flags:");
+	for (name, result) in &report.setup_flags {
+		match result {
+			Ok(()) => println!("  {:<28} ok", name),
+			Err(e) => println!("  {:<28} failed: {}", name, e),
+		}
+	}
+	println!();
+	match report.probe {
+		Ok((last_op, ref ops)) => {
+			println!("opcodes (last_op = {}):", last_op.0);
+			for op in ops {
+				println!("  {:>3} {:<16} {}", op.opcode, opcode_name(op), if op.supported { "supported" } else { "not supported" });
+			}
+		},
+		Err(ref e) => println!("opcodes: probe failed: {}", e),
+	}
+}
+
+fn json_string(out: &mut String, s: &str) {
+	out.push('"');
+	for c in s.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); },
+			c => out.push(c),
+		}
+	}
+	out.push('"');
+}
+
+// `"error": "..."` member if result failed; returns whether it failed
+fn json_error<T>(out: &mut String, result: &io::Result<T>) -> bool {
+	match result {
+		Ok(_) => false,
+		Err(e) => {
+			out.push_str("\"error\":");
+			json_string(out, &e.to_string());
+			true
+		},
+	}
+}
+
+fn print_json(report: &Report) {
+	let params = &report.params;
+	let mut out = String::new();
+
+	out.push_str("{\"kernel\":");
+	json_string(&mut out, &report.kernel);
+	let _ = write!(out,
+		",\"setup\":{{\"entries\":{},\"sq_entries\":{},\"cq_entries\":{},\"features\":{}",
+		report.entries, params.sq_entries, params.cq_entries, params.features.bits(),
+	);
+	let _ = write!(out,
+		",\"sq_off\":{{\"head\":{},\"tail\":{},\"ring_mask\":{},\"ring_entries\":{},\"flags\":{},\"dropped\":{},\"array\":{}}}",
+		params.sq_off.head, params.sq_off.tail, params.sq_off.ring_mask, params.sq_off.ring_entries,
+		params.sq_off.flags, params.sq_off.dropped, params.sq_off.array,
+	);
+	let _ = write!(out,
+		",\"cq_off\":{{\"head\":{},\"tail\":{},\"ring_mask\":{},\"ring_entries\":{},\"overflow\":{},\"cqes\":{}}}}}",
+		params.cq_off.head, params.cq_off.tail, params.cq_off.ring_mask, params.cq_off.ring_entries,
+		params.cq_off.overflow, params.cq_off.cqes,
+	);
+
+	out.push_str(",\"features\":{");
+	for (i, &(name, feature)) in FEATURES.iter().enumerate() {
+		if i > 0 { out.push(','); }
+		json_string(&mut out, name);
+		let _ = write!(out, ":{}", params.features.contains(feature));
+	}
+	out.push('}');
+
+	out.push_str(",\"clamped\":{");
+	if let Ok((sq, cq)) = report.clamped {
+		let _ = write!(out, "\"sq_entries\":{},\"cq_entries\":{}", sq, cq);
+	}
+	json_error(&mut out, &report.clamped);
+	out.push('}');
+
+	out.push_str(",\"setup_flags\":{");
+	for (i, (name, result)) in report.setup_flags.iter().enumerate() {
+		if i > 0 { out.push(','); }
+		json_string(&mut out, name);
+		out.push_str(":{");
+		if !json_error(&mut out, result) {
+			out.push_str("\"ok\":true");
+		} else {
+			out.push_str(",\"ok\":false");
+		}
+		out.push('}');
+	}
+	out.push('}');
+
+	out.push_str(",\"probe\":{");
+	if let Ok((last_op, ref ops)) = report.probe {
+		let _ = write!(out, "\"last_op\":{},\"opcodes\":[", last_op.0);
+		for (i, op) in ops.iter().enumerate() {
+			if i > 0 { out.push(','); }
+			let _ = write!(out, "{{\"opcode\":{},\"name\":", op.opcode);
+			match op.name {
+				Some(ref name) => json_string(&mut out, name),
+				None => out.push_str("null"),
+			}
+			let _ = write!(out, ",\"supported\":{}}}", op.supported);
+		}
+		out.push(']');
+	}
+	json_error(&mut out, &report.probe);
+	out.push_str("}}");
+
+	println!("{}", out);
+}
+
+fn usage() -> ! {
+	eprintln!("Usage: uring-info [--json] [--entries N]");
+	std::process::exit(2);
+}
+
+fn main() {
+	let mut json = false;
+	let mut entries = 8;
+
+	let mut args = std::env::args().skip(1);
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--json" => json = true,
+			"--entries" => {
+				entries = match args.next().and_then(|n| n.parse().ok()) {
+					Some(n) => n,
+					None => usage(),
+				};
+			},
+			_ => usage(),
+		}
+	}
+
+	let report = match collect(entries) {
+		Ok(report) => report,
+		Err(e) => {
+			eprintln!("uring-info: io_uring setup failed: {}", e);
+			std::process::exit(1);
+		},
+	};
+
+	if json {
+		print_json(&report);
+	} else {
+		print_text(&report);
+	}
+}
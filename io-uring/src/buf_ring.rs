@@ -0,0 +1,145 @@
+use std::fmt;
+use std::io;
+use std::sync::atomic::{
+	AtomicU16,
+	Ordering,
+};
+
+use crate::{
+	BufferRingEntry,
+	UringFile,
+};
+use crate::mmap::MappedMemory;
+
+/// Ring of provided buffers (`IORING_REGISTER_PBUF_RING`)
+///
+/// Owns the ring and `count` buffers of `buf_len` bytes; all buffers
+/// are handed to the kernel on registration. Operations with
+/// `SubmissionEntryFlags::BUFFER_SELECT` and this group consume a
+/// buffer when they need one and report its id in the completion;
+/// `recycle` hands it back to the kernel.
+///
+/// The ring is unregistered on drop; no operation must be using its
+/// buffers anymore at that point.
+pub struct BufferRing {
+	// separate fd for the uring, so the ring can be unregistered on drop
+	file: UringFile,
+	ring: MappedMemory,
+	buffers: MappedMemory,
+	bgid: u16,
+	count: u16,
+	buf_len: usize,
+	// local copy of the ring tail
+	tail: u16,
+}
+
+impl BufferRing {
+	/// allocate `count` buffers of `buf_len` bytes each and register
+	/// them as group `bgid` with `file`
+	///
+	/// `count` must be a power of 2. Fails with `EINVAL` on kernels
+	/// before 5.19 (see `SubmissionEntry::provide_buffers` for those).
+	pub fn register(file: &UringFile, bgid: u16, count: u16, buf_len: usize) -> io::Result<Self> {
+		if !count.is_power_of_two() {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "buffer count must be a power of 2"));
+		}
+		if 0 == buf_len || buf_len > u32::max_value() as usize {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid buffer length"));
+		}
+		let total = buf_len.checked_mul(count as usize)
+			.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "buffers too large"))?;
+
+		let mut file = file.try_clone()?;
+		// mmap returns page aligned memory (required by the kernel)
+		let ring = MappedMemory::anonymous((count as usize) * std::mem::size_of::<BufferRingEntry>())?;
+		let buffers = MappedMemory::anonymous(total)?;
+		// only build the `BufferRing` (which unregisters on drop) once
+		// registration succeeded; on failure the group might belong to
+		// someone else.
+		unsafe {
+			file.register_buf_ring(ring.as_mut_ptr() as *mut BufferRingEntry, count as u32, bgid)?;
+		}
+
+		let mut ring = BufferRing {
+			file,
+			ring,
+			buffers,
+			bgid,
+			count,
+			buf_len,
+			tail: 0,
+		};
+		for bid in 0..count {
+			ring.push(bid);
+		}
+		ring.publish();
+		Ok(ring)
+	}
+
+	/// buffer group id
+	pub fn bgid(&self) -> u16 {
+		self.bgid
+	}
+
+	/// number of buffers
+	pub fn count(&self) -> u16 {
+		self.count
+	}
+
+	/// size of each buffer
+	pub fn buf_len(&self) -> usize {
+		self.buf_len
+	}
+
+	/// start of buffer `bid` (`buf_len` bytes)
+	pub fn buffer(&self, bid: u16) -> *mut u8 {
+		assert!(bid < self.count);
+		(self.buffers.as_mut_ptr() as usize + (bid as usize) * self.buf_len) as *mut u8
+	}
+
+	/// hand buffer `bid` back to the kernel
+	///
+	/// The buffer must have been consumed by a completed operation (and
+	/// must not be in use anymore).
+	pub unsafe fn recycle(&mut self, bid: u16) {
+		assert!(bid < self.count);
+		self.push(bid);
+		self.publish();
+	}
+
+	fn push(&mut self, bid: u16) {
+		let ndx = (self.tail & (self.count - 1)) as usize;
+		let entry = (self.ring.as_mut_ptr() as usize + ndx * std::mem::size_of::<BufferRingEntry>()) as *mut BufferRingEntry;
+		// don't overwrite the reserved field; in the first entry it is
+		// the tail
+		unsafe {
+			(*entry).addr = self.buffer(bid) as usize as u64;
+			(*entry).len = self.buf_len as u32;
+			(*entry).bid = bid;
+		}
+		self.tail = self.tail.wrapping_add(1);
+	}
+
+	fn publish(&self) {
+		let tail = self.ring.get_field::<AtomicU16>(BufferRingEntry::TAIL_OFFSET as u32);
+		unsafe { &*tail }.store(self.tail, Ordering::Release);
+	}
+}
+
+impl Drop for BufferRing {
+	fn drop(&mut self) {
+		if let Err(e) = self.file.unregister_buf_ring(self.bgid) {
+			log::error!("failed to unregister buffer ring {}: {}", self.bgid, e);
+		}
+	}
+}
+
+impl fmt::Debug for BufferRing {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("BufferRing")
+			.field("bgid", &self.bgid)
+			.field("count", &self.count)
+			.field("buf_len", &self.buf_len)
+			.finish()
+	}
+}
@@ -0,0 +1,233 @@
+use std::io;
+use std::os::unix::io::{
+	AsRawFd,
+	RawFd,
+};
+
+use crate::{
+	SetupFlags,
+	SetupParameters,
+	Uring,
+};
+
+/// Build a `Uring` with validated setup parameters
+///
+/// Invalid flag combinations are rejected with
+/// `io::ErrorKind::InvalidInput` before calling `io_uring_setup`.
+#[derive(Clone, Copy, Debug)]
+pub struct UringBuilder {
+	entries: u32,
+	params: SetupParameters,
+}
+
+impl UringBuilder {
+	/// `IORING_MAX_ENTRIES`
+	pub const MAX_ENTRIES: u32 = 32768;
+	/// `IORING_MAX_CQ_ENTRIES`
+	pub const MAX_CQ_ENTRIES: u32 = 2 * Self::MAX_ENTRIES;
+
+	/// flags that need a different ring layout than `Uring` implements
+	const UNSUPPORTED_FLAGS: SetupFlags = SetupFlags::from_bits_truncate(0
+		| SetupFlags::SQE128.bits()
+		| SetupFlags::CQE32.bits()
+		| SetupFlags::NO_MMAP.bits()
+		| SetupFlags::REGISTERED_FD_ONLY.bits()
+		| SetupFlags::NO_SQARRAY.bits()
+	);
+
+	/// `entries`: (minimum) size of the submission queue
+	pub fn new(entries: u32) -> Self {
+		UringBuilder {
+			entries,
+			params: SetupParameters::default(),
+		}
+	}
+
+	/// raw setup flags (in addition to those already set)
+	pub fn flags(&mut self, flags: SetupFlags) -> &mut Self {
+		self.params.flags |= flags;
+		self
+	}
+
+	/// busy-poll for completions (`IOPOLL`); only for `O_DIRECT` files
+	pub fn iopoll(&mut self) -> &mut Self {
+		self.flags(SetupFlags::IOPOLL)
+	}
+
+	/// kernel thread polls the submission queue (`SQPOLL`); it goes to
+	/// sleep after `idle_ms` milliseconds without submissions.
+	pub fn sqpoll(&mut self, idle_ms: u32) -> &mut Self {
+		self.params.sq_thread_idle = idle_ms;
+		self.flags(SetupFlags::SQPOLL)
+	}
+
+	/// pin the `SQPOLL` thread to `cpu` (`SQ_AFF`)
+	pub fn sqpoll_cpu(&mut self, cpu: u32) -> &mut Self {
+		self.params.sq_thread_cpu = cpu;
+		self.flags(SetupFlags::SQ_AFF)
+	}
+
+	/// (minimum) size of the completion queue (`CQSIZE`); must not be
+	/// smaller than the submission queue.
+	pub fn cq_entries(&mut self, entries: u32) -> &mut Self {
+		self.params.cq_entries = entries;
+		self.flags(SetupFlags::CQSIZE)
+	}
+
+	/// clamp queue sizes to the maximum instead of failing (`CLAMP`)
+	pub fn clamp(&mut self) -> &mut Self {
+		self.flags(SetupFlags::CLAMP)
+	}
+
+	/// share async worker pool with existing `uring` (`ATTACH_WQ`)
+	pub fn attach_wq<U: AsRawFd>(&mut self, uring: &U) -> &mut Self {
+		self.attach_wq_fd(uring.as_raw_fd())
+	}
+
+	/// share async worker pool with existing uring fd `fd` (`ATTACH_WQ`)
+	pub fn attach_wq_fd(&mut self, fd: RawFd) -> &mut Self {
+		self.params.wq_fd = fd as u32;
+		self.flags(SetupFlags::ATTACH_WQ)
+	}
+
+	/// start with the ring disabled (`R_DISABLED`)
+	pub fn disabled(&mut self) -> &mut Self {
+		self.flags(SetupFlags::R_DISABLED)
+	}
+
+	/// continue submitting the batch if an entry fails (`SUBMIT_ALL`)
+	pub fn submit_all(&mut self) -> &mut Self {
+		self.flags(SetupFlags::SUBMIT_ALL)
+	}
+
+	/// don't interrupt tasks to run completion task work
+	/// (`COOP_TASKRUN`); with `taskrun_flag` the kernel signals pending
+	/// task work in the SQ ring flags (`TASKRUN_FLAG`).
+	pub fn coop_taskrun(&mut self, taskrun_flag: bool) -> &mut Self {
+		if taskrun_flag {
+			self.flags(SetupFlags::TASKRUN_FLAG);
+		}
+		self.flags(SetupFlags::COOP_TASKRUN)
+	}
+
+	/// only a single task submits (`SINGLE_ISSUER`)
+	pub fn single_issuer(&mut self) -> &mut Self {
+		self.flags(SetupFlags::SINGLE_ISSUER)
+	}
+
+	/// run task work only when waiting for completions
+	/// (`DEFER_TASKRUN`); also needs `single_issuer`.
+	pub fn defer_taskrun(&mut self) -> &mut Self {
+		self.flags(SetupFlags::DEFER_TASKRUN)
+	}
+
+	/// check flag combinations and sizes
+	pub fn validate(&self) -> io::Result<()> {
+		fn invalid(msg: &str) -> io::Result<()> {
+			Err(io::Error::new(io::ErrorKind::InvalidInput, msg))
+		}
+
+		let flags = self.params.flags;
+		let clamp = flags.contains(SetupFlags::CLAMP);
+
+		if flags.intersects(Self::UNSUPPORTED_FLAGS) {
+			return invalid("setup flags need a ring layout not supported by Uring");
+		}
+
+		if 0 == self.entries {
+			return invalid("submission queue needs at least one entry");
+		}
+		if !clamp && self.entries > Self::MAX_ENTRIES {
+			return invalid("too many submission queue entries (use CLAMP)");
+		}
+
+		if flags.contains(SetupFlags::CQSIZE) {
+			let cq_entries = self.params.cq_entries;
+			if 0 == cq_entries {
+				return invalid("completion queue needs at least one entry");
+			}
+			if !clamp && cq_entries > Self::MAX_CQ_ENTRIES {
+				return invalid("too many completion queue entries (use CLAMP)");
+			}
+			// the kernel rounds both sizes up to a power of two first
+			let cq_entries = std::cmp::min(cq_entries, Self::MAX_CQ_ENTRIES).next_power_of_two();
+			if cq_entries < std::cmp::min(self.entries, Self::MAX_ENTRIES).next_power_of_two() {
+				return invalid("completion queue can't be smaller than submission queue");
+			}
+		}
+
+		if flags.contains(SetupFlags::SQ_AFF) && !flags.contains(SetupFlags::SQPOLL) {
+			return invalid("SQ_AFF requires SQPOLL");
+		}
+
+		let taskrun = SetupFlags::COOP_TASKRUN | SetupFlags::TASKRUN_FLAG | SetupFlags::DEFER_TASKRUN;
+		if flags.contains(SetupFlags::SQPOLL) && flags.intersects(taskrun) {
+			return invalid("COOP_TASKRUN, TASKRUN_FLAG and DEFER_TASKRUN can't be used with SQPOLL");
+		}
+		if flags.contains(SetupFlags::TASKRUN_FLAG) && !flags.intersects(SetupFlags::COOP_TASKRUN | SetupFlags::DEFER_TASKRUN) {
+			return invalid("TASKRUN_FLAG requires COOP_TASKRUN or DEFER_TASKRUN");
+		}
+		if flags.contains(SetupFlags::DEFER_TASKRUN) && !flags.contains(SetupFlags::SINGLE_ISSUER) {
+			return invalid("DEFER_TASKRUN requires SINGLE_ISSUER");
+		}
+
+		Ok(())
+	}
+
+	/// the parameters `build` passes to `io_uring_setup`
+	pub fn params(&self) -> &SetupParameters {
+		&self.params
+	}
+
+	pub fn build(&self) -> io::Result<Uring> {
+		self.validate()?;
+		Uring::new(self.entries, self.params)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn rejected(builder: &UringBuilder) -> bool {
+		match builder.validate() {
+			Err(e) => e.kind() == io::ErrorKind::InvalidInput,
+			Ok(()) => false,
+		}
+	}
+
+	#[test]
+	fn entries() {
+		assert!(UringBuilder::new(8).validate().is_ok());
+		assert!(rejected(&UringBuilder::new(0)));
+		assert!(rejected(&UringBuilder::new(UringBuilder::MAX_ENTRIES + 1)));
+		assert!(UringBuilder::new(UringBuilder::MAX_ENTRIES + 1).clamp().validate().is_ok());
+	}
+
+	#[test]
+	fn cq_entries() {
+		assert!(UringBuilder::new(8).cq_entries(16).validate().is_ok());
+		assert!(UringBuilder::new(8).cq_entries(8).validate().is_ok());
+		// both round up to 8
+		assert!(UringBuilder::new(8).cq_entries(5).validate().is_ok());
+		assert!(UringBuilder::new(5).cq_entries(8).validate().is_ok());
+		assert!(rejected(UringBuilder::new(8).cq_entries(4)));
+		assert!(rejected(UringBuilder::new(5).cq_entries(4)));
+		assert!(rejected(UringBuilder::new(8).cq_entries(0)));
+		assert!(rejected(UringBuilder::new(8).cq_entries(UringBuilder::MAX_CQ_ENTRIES + 1)));
+		assert!(UringBuilder::new(8).cq_entries(UringBuilder::MAX_CQ_ENTRIES + 1).clamp().validate().is_ok());
+	}
+
+	#[test]
+	fn flag_combinations() {
+		assert!(rejected(UringBuilder::new(8).flags(SetupFlags::SQE128)));
+		assert!(rejected(UringBuilder::new(8).sqpoll_cpu(0)));
+		assert!(UringBuilder::new(8).sqpoll(1000).sqpoll_cpu(0).validate().is_ok());
+		assert!(rejected(UringBuilder::new(8).sqpoll(1000).coop_taskrun(false)));
+		assert!(UringBuilder::new(8).coop_taskrun(true).validate().is_ok());
+		assert!(rejected(UringBuilder::new(8).flags(SetupFlags::TASKRUN_FLAG)));
+		assert!(rejected(UringBuilder::new(8).defer_taskrun()));
+		assert!(UringBuilder::new(8).single_issuer().defer_taskrun().validate().is_ok());
+		assert!(UringBuilder::new(8).single_issuer().defer_taskrun().flags(SetupFlags::TASKRUN_FLAG).validate().is_ok());
+	}
+}
@@ -0,0 +1,214 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::ops::{
+	Deref,
+	DerefMut,
+};
+use std::rc::Rc;
+use std::slice::SliceIndex;
+
+use crate::{
+	FileDescriptor,
+	IoPriority,
+	ReadWriteFlags,
+	SubmissionEntry,
+	UringFile,
+};
+use crate::mmap::MappedMemory;
+
+struct Shared {
+	memory: MappedMemory,
+	// distance between buffers (buf_len rounded up to page size)
+	stride: usize,
+	buf_len: usize,
+	count: u16,
+	free: RefCell<Vec<u16>>,
+}
+
+impl Shared {
+	fn buf_ptr(&self, index: u16) -> *mut u8 {
+		debug_assert!(index < self.count);
+		(self.memory.as_mut_ptr() as usize + (index as usize) * self.stride) as *mut u8
+	}
+}
+
+/// Buffers registered with a uring (`IORING_REGISTER_BUFFERS`)
+///
+/// Owns the (page-aligned) memory and hands out `FixedBuf` slots. The
+/// memory stays mapped while the `FixedBuffers` or any `FixedBuf` is
+/// alive; the kernel keeps its own reference to the pages until the
+/// buffers are unregistered.
+///
+/// Only one set of buffers can be registered with a uring at a time.
+pub struct FixedBuffers(Rc<Shared>);
+
+impl FixedBuffers {
+	/// allocate `count` buffers of `buf_len` bytes each and register
+	/// them with `file`
+	///
+	/// each buffer starts on a page boundary.
+	pub fn register(file: &mut UringFile, count: u16, buf_len: usize) -> io::Result<Self> {
+		let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+		if 0 == count || 0 == buf_len {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "need at least one non-empty buffer"));
+		}
+		let stride = buf_len.checked_add(page_size - 1)
+			.map(|len| len & !(page_size - 1))
+			.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "buffer too large"))?;
+		let total = stride.checked_mul(count as usize)
+			.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "buffers too large"))?;
+
+		let shared = Shared {
+			memory: MappedMemory::anonymous(total)?,
+			stride,
+			buf_len,
+			count,
+			// hand out low indices first
+			free: RefCell::new((0..count).rev().collect()),
+		};
+
+		let iovecs: Vec<libc::iovec> = (0..count).map(|index| libc::iovec {
+			iov_base: shared.buf_ptr(index) as *mut libc::c_void,
+			iov_len: buf_len,
+		}).collect();
+		unsafe {
+			file.register_buffers(&iovecs)?;
+		}
+
+		Ok(FixedBuffers(Rc::new(shared)))
+	}
+
+	/// unregister buffers from `file`
+	///
+	/// handed out `FixedBuf`s stay usable as memory, but must not be
+	/// used for fixed operations anymore.
+	pub fn unregister(self, file: &mut UringFile) -> io::Result<()> {
+		file.unregister_buffers()
+	}
+
+	/// number of registered buffers
+	pub fn count(&self) -> u16 {
+		self.0.count
+	}
+
+	/// size of each buffer
+	pub fn buf_len(&self) -> usize {
+		self.0.buf_len
+	}
+
+	/// number of buffers not handed out
+	pub fn available(&self) -> usize {
+		self.0.free.borrow().len()
+	}
+
+	/// take a free buffer
+	pub fn get(&self) -> Option<FixedBuf> {
+		let index = self.0.free.borrow_mut().pop()?;
+		Some(FixedBuf {
+			shared: self.0.clone(),
+			index,
+		})
+	}
+}
+
+impl fmt::Debug for FixedBuffers {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("FixedBuffers")
+			.field("count", &self.0.count)
+			.field("buf_len", &self.0.buf_len)
+			.field("available", &self.available())
+			.finish()
+	}
+}
+
+/// Slot in `FixedBuffers`; returned to the pool on drop
+///
+/// Dereferences to the complete buffer.
+pub struct FixedBuf {
+	shared: Rc<Shared>,
+	index: u16,
+}
+
+impl FixedBuf {
+	/// index of the buffer in the registered set
+	pub fn buf_index(&self) -> u16 {
+		self.index
+	}
+
+	/// prepare a `READ_FIXED` of `range` of this buffer
+	///
+	/// Panics if `range` is out of bounds.
+	///
+	/// # Safety
+	///
+	/// The kernel writes to the buffer until the operation completed;
+	/// the `FixedBuf` must be kept (and not be accessed) until then.
+	pub unsafe fn read_fixed<R>(&mut self, entry: &mut SubmissionEntry, prio: IoPriority, fd: FileDescriptor, offset: u64, flags: ReadWriteFlags, range: R)
+	where
+		R: SliceIndex<[u8], Output = [u8]>,
+	{
+		let index = self.index;
+		let buf: *const [u8] = &mut self[range];
+		// buf is a sub-slice of the buffer registered at `index`
+		entry.read_fixed(prio, fd, offset, flags, index, buf);
+	}
+
+	/// prepare a `WRITE_FIXED` of `range` of this buffer
+	///
+	/// Panics if `range` is out of bounds.
+	///
+	/// # Safety
+	///
+	/// The kernel reads from the buffer until the operation completed;
+	/// the `FixedBuf` must be kept (and not be modified) until then.
+	pub unsafe fn write_fixed<R>(&self, entry: &mut SubmissionEntry, prio: IoPriority, fd: FileDescriptor, offset: u64, flags: ReadWriteFlags, range: R)
+	where
+		R: SliceIndex<[u8], Output = [u8]>,
+	{
+		let buf: *const [u8] = &self[range];
+		// buf is a sub-slice of the buffer registered at `index`
+		entry.write_fixed(prio, fd, offset, flags, self.index, buf);
+	}
+}
+
+impl Deref for FixedBuf {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		unsafe { std::slice::from_raw_parts(self.shared.buf_ptr(self.index), self.shared.buf_len) }
+	}
+}
+
+impl DerefMut for FixedBuf {
+	fn deref_mut(&mut self) -> &mut [u8] {
+		unsafe { std::slice::from_raw_parts_mut(self.shared.buf_ptr(self.index), self.shared.buf_len) }
+	}
+}
+
+impl AsRef<[u8]> for FixedBuf {
+	fn as_ref(&self) -> &[u8] {
+		self
+	}
+}
+
+impl AsMut<[u8]> for FixedBuf {
+	fn as_mut(&mut self) -> &mut [u8] {
+		self
+	}
+}
+
+impl Drop for FixedBuf {
+	fn drop(&mut self) {
+		self.shared.free.borrow_mut().push(self.index);
+	}
+}
+
+impl fmt::Debug for FixedBuf {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("FixedBuf")
+			.field("buf_index", &self.index)
+			.field("len", &self.shared.buf_len)
+			.finish()
+	}
+}
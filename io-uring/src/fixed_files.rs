@@ -0,0 +1,160 @@
+use std::cell::{
+	Cell,
+	RefCell,
+};
+use std::fmt;
+use std::io;
+use std::os::unix::io::{
+	AsRawFd,
+	RawFd,
+};
+use std::rc::Rc;
+
+use crate::{
+	FileDescriptor,
+	UringFile,
+};
+
+struct Shared {
+	// separate fd for the uring, so slots can be updated on drop
+	file: RefCell<UringFile>,
+	count: u32,
+	free: RefCell<Vec<u32>>,
+	registered: Cell<bool>,
+}
+
+impl Shared {
+	fn update(&self, slot: u32, fd: RawFd) -> io::Result<()> {
+		if !self.registered.get() {
+			return Err(io::Error::new(io::ErrorKind::Other, "fixed files unregistered"));
+		}
+		self.file.borrow_mut().update_files(slot, &[fd])?;
+		Ok(())
+	}
+}
+
+/// Table of registered files (`IORING_REGISTER_FILES`) with slot
+/// allocation
+///
+/// Slots are handed out as `FixedFile`, which clears the slot on drop.
+/// The kernel keeps its own reference to registered files, so the
+/// original fd can be closed after inserting it.
+///
+/// Only one file table can be registered with a uring at a time.
+pub struct FixedFiles(Rc<Shared>);
+
+impl FixedFiles {
+	/// register a table of `count` empty slots with the uring
+	pub fn register(file: &UringFile, count: u32) -> io::Result<Self> {
+		let mut file = file.try_clone()?;
+		if let Err(e) = file.register_files_sparse(count) {
+			if e.raw_os_error() != Some(libc::EINVAL) {
+				return Err(e);
+			}
+			// kernel before 5.19; use -1 for empty slots
+			file.register_files(&vec![-1; count as usize])?;
+		}
+
+		Ok(FixedFiles(Rc::new(Shared {
+			file: RefCell::new(file),
+			count,
+			// hand out low slots first
+			free: RefCell::new((0..count).rev().collect()),
+			registered: Cell::new(true),
+		})))
+	}
+
+	/// unregister the table
+	///
+	/// dropping handed out `FixedFile`s is fine afterwards, but they
+	/// must not be used anymore.
+	pub fn unregister(self) -> io::Result<()> {
+		self.0.registered.set(false);
+		self.0.file.borrow_mut().unregister_files()
+	}
+
+	/// number of slots
+	pub fn count(&self) -> u32 {
+		self.0.count
+	}
+
+	/// number of slots not handed out
+	pub fn available(&self) -> usize {
+		self.0.free.borrow().len()
+	}
+
+	/// reserve an empty slot
+	///
+	/// can be filled by operations opening files directly into a fixed
+	/// slot (e.g. `openat` with `file_index`) or `FixedFile::replace`.
+	pub fn reserve(&self) -> Option<FixedFile> {
+		let slot = self.0.free.borrow_mut().pop()?;
+		Some(FixedFile {
+			shared: self.0.clone(),
+			slot,
+		})
+	}
+
+	/// register `file` in a free slot
+	pub fn insert<F: AsRawFd>(&self, file: &F) -> io::Result<FixedFile> {
+		let mut fixed = self.reserve().ok_or_else(|| {
+			io::Error::new(io::ErrorKind::Other, "no free fixed file slot")
+		})?;
+		fixed.replace(file)?;
+		Ok(fixed)
+	}
+}
+
+impl fmt::Debug for FixedFiles {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("FixedFiles")
+			.field("count", &self.0.count)
+			.field("available", &self.available())
+			.finish()
+	}
+}
+
+/// Slot in `FixedFiles`; cleared and returned to the table on drop
+///
+/// Operations already submitted with the slot keep using the file they
+/// started with.
+pub struct FixedFile {
+	shared: Rc<Shared>,
+	slot: u32,
+}
+
+impl FixedFile {
+	/// index in the registered table
+	pub fn slot(&self) -> u32 {
+		self.slot
+	}
+
+	/// descriptor to use in submission entries
+	pub fn fd(&self) -> FileDescriptor {
+		FileDescriptor::Fixed(self.slot)
+	}
+
+	/// register `file` in this slot (replacing the previous one)
+	pub fn replace<F: AsRawFd>(&mut self, file: &F) -> io::Result<()> {
+		self.shared.update(self.slot, file.as_raw_fd())
+	}
+}
+
+impl Drop for FixedFile {
+	fn drop(&mut self) {
+		if self.shared.registered.get() {
+			if let Err(e) = self.shared.update(self.slot, -1) {
+				// don't reuse slot; it might still contain a file
+				log::error!("failed to clear fixed file slot {}: {}", self.slot, e);
+				return;
+			}
+		}
+		self.shared.free.borrow_mut().push(self.slot);
+	}
+}
+
+impl fmt::Debug for FixedFile {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("FixedFile").field(&self.slot).finish()
+	}
+}
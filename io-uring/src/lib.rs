@@ -1,4 +1,9 @@
+mod buf_ring;
+mod builder;
+mod fixed_files;
+mod fixed_buffers;
 mod mmap;
+mod probe;
 
 use std::sync::atomic::{
 	Ordering,
@@ -12,12 +17,26 @@ use std::os::unix::io::{
 	RawFd,
 };
 use std::mem::size_of;
+use std::time::Duration;
+use std::rc::Rc;
 
 pub use io_uring_sys::*;
+pub use crate::buf_ring::BufferRing;
+pub use crate::builder::UringBuilder;
+pub use crate::fixed_buffers::{
+	FixedBuf,
+	FixedBuffers,
+};
+pub use crate::fixed_files::{
+	FixedFile,
+	FixedFiles,
+};
+pub use crate::probe::Probe;
 use crate::mmap::MappedMemory;
 
 pub struct Uring {
 	file: UringFile,
+	features: Features,
 	sq: SubmissionQueue,
 	cq: CompletionQueue,
 }
@@ -25,20 +44,127 @@ pub struct Uring {
 impl Uring {
 	pub fn new(entries: u32, mut params: SetupParameters) -> io::Result<Self> {
 		let file = UringFile::new(entries, &mut params)?;
-		let sq = SubmissionQueue::new(&file, &params.sq_off, params.sq_entries)?;
-		let cq = CompletionQueue::new(&file, &params.cq_off, params.cq_entries)?;
+		let sq_ring_size = SubmissionQueue::ring_size(&params.sq_off, params.sq_entries);
+		let cq_ring_size = CompletionQueue::ring_size(&params.cq_off, params.cq_entries);
+		let (sq_ring, cq_ring) = if params.features.contains(Features::SINGLE_MMAP) {
+			// both rings live in the same mapping
+			let ring = Rc::new(MappedMemory::map(
+				file.as_raw_fd(),
+				SetupParameters::SUBMISSION_QUEUE_RING_OFFSET,
+				std::cmp::max(sq_ring_size, cq_ring_size),
+			)?);
+			(ring.clone(), ring)
+		} else {
+			let sq_ring = MappedMemory::map(
+				file.as_raw_fd(),
+				SetupParameters::SUBMISSION_QUEUE_RING_OFFSET,
+				sq_ring_size,
+			)?;
+			let cq_ring = MappedMemory::map(
+				file.as_raw_fd(),
+				SetupParameters::COMPLETION_QUEUE_RING_OFFSET,
+				cq_ring_size,
+			)?;
+			(Rc::new(sq_ring), Rc::new(cq_ring))
+		};
+		let sq = SubmissionQueue::new(&file, sq_ring, &params.sq_off, params.sq_entries)?;
+		let cq = CompletionQueue::new(cq_ring, &params.cq_off, params.cq_entries)?;
 
 		Ok(Uring {
 			file,
+			features: params.features,
 			sq,
 			cq,
 		})
 	}
 
+	/// features reported by the kernel on setup
+	pub fn features(&self) -> Features {
+		self.features
+	}
+
 	pub fn file(&mut self) -> &mut UringFile {
 		&mut self.file
 	}
 
+	/// submit staged entries and wait for `min_complete` completions
+	///
+	/// Retries if interrupted by a signal. If the kernel doesn't accept
+	/// new entries until completions are reaped (`EBUSY`, `EAGAIN`),
+	/// `reap` is called with the completion queue (returning whether it
+	/// found any completions) and the submission is retried; reaped
+	/// completions count as the completions to wait for.
+	///
+	/// If `reap` doesn't find anything this returns without waiting;
+	/// `staged` entries are submitted with the next call.
+	pub fn submit_and_wait<R>(&mut self, min_complete: u32, reap: R) -> io::Result<SubmitStatus>
+	where
+		R: FnMut(&mut CompletionQueue) -> bool,
+	{
+		self.submit_and_wait_inner(min_complete, None, reap)
+	}
+
+	/// like `submit_and_wait`, but stops waiting after `timeout`
+	/// (requires `Features::EXT_ARG`)
+	pub fn submit_and_wait_timeout<R>(&mut self, min_complete: u32, timeout: Duration, reap: R) -> io::Result<SubmitStatus>
+	where
+		R: FnMut(&mut CompletionQueue) -> bool,
+	{
+		self.submit_and_wait_inner(min_complete, Some(timeout), reap)
+	}
+
+	fn submit_and_wait_inner<R>(&mut self, mut min_complete: u32, timeout: Option<Duration>, mut reap: R) -> io::Result<SubmitStatus>
+	where
+		R: FnMut(&mut CompletionQueue) -> bool,
+	{
+		loop {
+			let pending = self.sq.pending_submissions();
+			let result = match timeout {
+				Some(timeout) if min_complete > 0 => {
+					self.file.enter_timeout(pending, min_complete, timeout, None)
+				},
+				_ => {
+					let flags = if min_complete > 0 { EnterFlags::GETEVENTS } else { EnterFlags::default() };
+					self.file.enter(pending, min_complete, flags, None)
+				},
+			};
+			match result {
+				Ok(submitted) => {
+					return Ok(SubmitStatus {
+						submitted,
+						staged: self.sq.pending_submissions(),
+					});
+				},
+				Err(e) => match e.raw_os_error() {
+					Some(libc::EINTR) => (),
+					Some(libc::EBUSY) | Some(libc::EAGAIN) => {
+						log::debug!("io_uring_enter: kernel busy, reaping completions: {}", e);
+						if !reap(&mut self.cq) {
+							return Ok(SubmitStatus {
+								submitted: 0,
+								staged: self.sq.pending_submissions(),
+							});
+						}
+						// got completions, don't wait for more
+						min_complete = 0;
+					},
+					_ => return Err(e),
+				},
+			}
+		}
+	}
+
+	/// move completions the kernel kept back on a full completion queue
+	/// (`SubmissionQueueFlags::CQ_OVERFLOW`) into the queue, as far as
+	/// there is space
+	pub fn flush_overflow(&mut self) -> io::Result<()> {
+		match self.file.enter(0, 0, EnterFlags::GETEVENTS, None) {
+			Ok(_) => Ok(()),
+			Err(ref e) if e.kind() == io::ErrorKind::Interrupted => Ok(()),
+			Err(e) => Err(e),
+		}
+	}
+
 	pub fn submission_queue(&mut self) -> &mut SubmissionQueue {
 		&mut self.sq
 	}
@@ -48,11 +174,21 @@ impl Uring {
 	}
 }
 
+/// Result of `Uring::submit_and_wait`
+#[derive(Clone, Copy, Debug)]
+pub struct SubmitStatus {
+	/// number of submission entries consumed by the kernel
+	pub submitted: u32,
+	/// number of submission entries still staged
+	pub staged: u32,
+}
+
 // the purpose of the indirection (index array -> sces) is not quite
 // clear; we don't need it and will keep the index simple, i.e. entry i
 // (masked) in ring will point at i in sces.
 pub struct SubmissionQueue {
-	_mmap: MappedMemory,
+	// might be shared with completion queue (`Features::SINGLE_MMAP`)
+	_mmap: Rc<MappedMemory>,
 
 	// `head` is controlled by kernel. we only need to update it if it
 	// seems the ring is full.
@@ -84,12 +220,11 @@ pub struct SubmissionQueue {
 }
 
 impl SubmissionQueue {
-	fn new(file: &UringFile, offsets: &SubmissionQueueRingOffsets, sq_entries: u32) -> io::Result<Self> {
-		let mmap = MappedMemory::map(
-			file.as_raw_fd(),
-			SetupParameters::SUBMISSION_QUEUE_RING_OFFSET,
-			(offsets.array as usize) + size_of::<u32>() * (sq_entries as usize),
-		)?;
+	fn ring_size(offsets: &SubmissionQueueRingOffsets, sq_entries: u32) -> usize {
+		(offsets.array as usize) + size_of::<u32>() * (sq_entries as usize)
+	}
+
+	fn new(file: &UringFile, mmap: Rc<MappedMemory>, offsets: &SubmissionQueueRingOffsets, sq_entries: u32) -> io::Result<Self> {
 		let mmap_entries = MappedMemory::map(
 			file.as_raw_fd(),
 			SetupParameters::SUBMISSION_QUEUE_ENTRIES_OFFSET,
@@ -160,6 +295,13 @@ impl SubmissionQueue {
 		&& 0 == ((self.cached_head ^ self.local_tail) & self.ring_mask) // point to same entry
 	}
 
+	/// number of entries that can still be staged before the queue is
+	/// full
+	pub fn available(&mut self) -> u32 {
+		let used = self.local_tail.wrapping_sub(self.refresh_head());
+		(self.ring_mask + 1) - used
+	}
+
 	pub fn bulk(&mut self) -> BulkSubmission {
 		BulkSubmission(self)
 	}
@@ -212,6 +354,10 @@ impl BulkSubmission<'_> {
 		self.0.is_full()
 	}
 
+	pub fn available(&mut self) -> u32 {
+		self.0.available()
+	}
+
 	pub fn submit_with<F, E>(&mut self, f: F) -> Result<(), SubmissionError<E>>
 	where
 		F: FnOnce(&mut SubmissionEntry) -> Result<(), E>
@@ -225,6 +371,43 @@ impl BulkSubmission<'_> {
 		self.0.local_tail = ndx.wrapping_add(1);
 		Ok(())
 	}
+
+	/// Submit a chain of `len` linked entries; either all or none get
+	/// queued.
+	///
+	/// `f` is called with the index in the chain (`0..len`) for each
+	/// entry; all but the last entry are linked with `IO_LINK` (unless
+	/// `f` already set `IO_HARDLINK`), links on the last entry are
+	/// removed.
+	///
+	/// Fails with `QueueFull` if there is no room for the complete
+	/// chain; if `f` fails for any entry the entries of this chain
+	/// already filled are discarded.
+	pub fn submit_chain_with<F, E>(&mut self, len: u32, mut f: F) -> Result<(), SubmissionError<E>>
+	where
+		F: FnMut(u32, &mut SubmissionEntry) -> Result<(), E>
+	{
+		if self.available() < len { return Err(SubmissionError::QueueFull); }
+		let chain_start = self.0.local_tail;
+		for i in 0..len {
+			let result = self.submit_with(|entry| {
+				f(i, entry)?;
+				let link = SubmissionEntryFlags::IO_LINK | SubmissionEntryFlags::IO_HARDLINK;
+				if i + 1 == len {
+					entry.flags.remove(link);
+				} else if !entry.flags.intersects(link) {
+					entry.flags |= SubmissionEntryFlags::IO_LINK;
+				}
+				Ok(())
+			});
+			if let Err(e) = result {
+				// tail isn't flushed yet, just forget about the chain
+				self.0.local_tail = chain_start;
+				return Err(e);
+			}
+		}
+		Ok(())
+	}
 }
 
 impl Drop for BulkSubmission<'_> {
@@ -234,7 +417,8 @@ impl Drop for BulkSubmission<'_> {
 }
 
 pub struct CompletionQueue {
-	_mmap: MappedMemory,
+	// might be shared with submission queue (`Features::SINGLE_MMAP`)
+	_mmap: Rc<MappedMemory>,
 
 	// updated by userspace; increment after entry at head was read; for
 	// bulk reading only increment local_head and write back alter
@@ -257,12 +441,11 @@ pub struct CompletionQueue {
 }
 
 impl CompletionQueue {
-	fn new(file: &UringFile, offsets: &CompletionQueueRingOffsets, cq_entries: u32) -> io::Result<Self> {
-		let mmap = MappedMemory::map(
-			file.as_raw_fd(),
-			SetupParameters::COMPLETION_QUEUE_RING_OFFSET,
-			(offsets.cqes as usize) + size_of::<CompletionEntry>() * (cq_entries as usize),
-		)?;
+	fn ring_size(offsets: &CompletionQueueRingOffsets, cq_entries: u32) -> usize {
+		(offsets.cqes as usize) + size_of::<CompletionEntry>() * (cq_entries as usize)
+	}
+
+	fn new(mmap: Rc<MappedMemory>, offsets: &CompletionQueueRingOffsets, cq_entries: u32) -> io::Result<Self> {
 		let k_head: &AtomicU32 = unsafe { &*mmap.get_field(offsets.head) };
 		let local_head = k_head.load(Ordering::Relaxed);
 		let k_tail: &AtomicU32 = unsafe { &*mmap.get_field(offsets.tail) };
@@ -357,15 +540,48 @@ impl UringFile {
 		Ok(unsafe { Self::from_raw_fd(res) })
 	}
 
-	pub fn enter(&mut self, to_submit: u32, min_complete: u32, flags: EnterFlags, sig: Option<&libc::sigset_t>) -> io::Result<()> {
+	/// like `enter` with `EnterFlags::GETEVENTS`, but stops waiting
+	/// after `timeout` (requires `Features::EXT_ARG`)
+	///
+	/// an elapsed timeout is not an error.
+	pub fn enter_timeout(&mut self, to_submit: u32, min_complete: u32, timeout: Duration, sig: Option<&libc::sigset_t>) -> io::Result<u32> {
+		let ts = KernelTimespec::from(timeout);
+		let arg = GeteventsArg {
+			sigmask: match sig {
+				Some(sig) => sig as *const _ as usize as u64,
+				None => 0,
+			},
+			sigmask_sz: KERNEL_SIGSET_SIZE as u32,
+			min_wait_usec: 0,
+			ts: &ts as *const _ as usize as u64,
+		};
+		let flags = EnterFlags::GETEVENTS | EnterFlags::EXT_ARG;
+		let arg_ptr = &arg as *const GeteventsArg as *const libc::c_void;
+		let res = unsafe { io_uring_enter2(self.as_raw_fd(), to_submit, min_complete, flags.bits(), arg_ptr, size_of::<GeteventsArg>()) };
+		if res < 0 {
+			let e = io::Error::last_os_error();
+			if e.raw_os_error() == Some(libc::ETIME) {
+				// nothing submitted (otherwise we'd get the count)
+				Ok(0)
+			} else {
+				Err(e)
+			}
+		} else {
+			Ok(res as u32)
+		}
+	}
+
+	/// returns number of consumed submission entries
+	pub fn enter(&mut self, to_submit: u32, min_complete: u32, flags: EnterFlags, sig: Option<&libc::sigset_t>) -> io::Result<u32> {
 		let sig = match sig {
 			Some(sig) => sig as *const _,
 			None => 0 as *const _,
 		};
-		if unsafe { io_uring_enter(self.as_raw_fd(), to_submit, min_complete, flags.bits(), sig) } < 0 {
+		let res = unsafe { io_uring_enter(self.as_raw_fd(), to_submit, min_complete, flags.bits(), sig) };
+		if res < 0 {
 			Err(io::Error::last_os_error())
 		} else {
-			Ok(())
+			Ok(res as u32)
 		}
 	}
 
@@ -374,37 +590,132 @@ impl UringFile {
 	///
 	/// unsafe because it passes raw pointers in the iovecs.
 	pub unsafe fn register_buffers(&mut self, buffers: &[libc::iovec]) -> io::Result<()> {
-		self.register(RegisterOpCode::REGISTER_BUFFERS, buffers.as_ptr() as *const _, buffers.len() as u32)
+		self.register(RegisterOpCode::REGISTER_BUFFERS, buffers.as_ptr() as *const _, buffers.len() as u32)?;
+		Ok(())
 	}
 
 	/// fails if there are currently no buffers registered.
 	pub fn unregister_buffers(&mut self) -> io::Result<()> {
 		unsafe {
-			self.register(RegisterOpCode::UNREGISTER_BUFFERS, 0 as *const _, 0)
+			self.register(RegisterOpCode::UNREGISTER_BUFFERS, 0 as *const _, 0)?;
 		}
+		Ok(())
 	}
 
 	/// can only register one list of fds at once; needs an explicit
 	/// unregister before registering the next list.
+	///
+	/// -1 entries are empty slots, which can be filled with
+	/// `update_files`.
 	pub fn register_files(&mut self, fds: &[RawFd]) -> io::Result<()> {
 		assert!(fds.len() <= u32::max_value() as usize);
 		unsafe {
-			self.register(RegisterOpCode::REGISTER_FILES, fds.as_ptr() as *const _, fds.len() as u32)
+			self.register(RegisterOpCode::REGISTER_FILES, fds.as_ptr() as *const _, fds.len() as u32)?;
+		}
+		Ok(())
+	}
+
+	/// like `register_files`, but with a tag for each fd (0: no tag)
+	///
+	/// once a tagged file is removed from the table (and no longer in
+	/// use) the kernel posts a completion with `user_data = tag`.
+	pub fn register_files_tagged(&mut self, fds: &[RawFd], tags: &[u64]) -> io::Result<()> {
+		assert!(fds.len() <= u32::max_value() as usize);
+		assert_eq!(fds.len(), tags.len());
+		let arg = ResourceRegister::new(
+			fds.len() as u32,
+			ResourceRegisterFlags::default(),
+			fds.as_ptr() as usize as u64,
+			tags.as_ptr() as usize as u64,
+		);
+		unsafe {
+			self.register(RegisterOpCode::REGISTER_FILES2, &arg as *const _ as *const _, size_of::<ResourceRegister>() as u32)?;
+		}
+		Ok(())
+	}
+
+	/// register a table of `count` empty slots
+	///
+	/// needs kernel 5.19; `register_files` with -1 entries works on
+	/// older kernels.
+	pub fn register_files_sparse(&mut self, count: u32) -> io::Result<()> {
+		let arg = ResourceRegister::new(count, ResourceRegisterFlags::SPARSE, 0, 0);
+		unsafe {
+			self.register(RegisterOpCode::REGISTER_FILES2, &arg as *const _ as *const _, size_of::<ResourceRegister>() as u32)?;
+		}
+		Ok(())
+	}
+
+	/// replace registered fds starting at slot `offset`
+	///
+	/// -1 clears a slot, `FilesUpdate::SKIP` leaves it unchanged.
+	/// Returns the number of updated slots.
+	pub fn update_files(&mut self, offset: u32, fds: &[RawFd]) -> io::Result<u32> {
+		assert!(fds.len() <= u32::max_value() as usize);
+		let arg = FilesUpdate::new(offset, fds);
+		unsafe {
+			self.register(RegisterOpCode::REGISTER_FILES_UPDATE, &arg as *const _ as *const _, fds.len() as u32)
 		}
 	}
 
+	/// only allocate slots in `offset..offset+len` for operations that
+	/// let the kernel pick a fixed file slot
+	pub fn register_file_alloc_range(&mut self, offset: u32, len: u32) -> io::Result<()> {
+		let arg = FileIndexRange::new(offset, len);
+		unsafe {
+			self.register(RegisterOpCode::REGISTER_FILE_ALLOC_RANGE, &arg as *const _ as *const _, 0)?;
+		}
+		Ok(())
+	}
+
 	/// fails if there is currently no fd set registered.
 	pub fn unregister_files(&mut self) -> io::Result<()> {
 		unsafe {
-			self.register(RegisterOpCode::UNREGISTER_FILES, 0 as *const _, 0)
+			self.register(RegisterOpCode::UNREGISTER_FILES, 0 as *const _, 0)?;
 		}
+		Ok(())
 	}
 
-	pub unsafe fn register(&self, opcode: RegisterOpCode, arg: *const libc::c_void, nr_args: u32) -> io::Result<()> {
-		if io_uring_register(self.as_raw_fd(), opcode.0, arg, nr_args) != 0 {
+	/// register a ring of provided buffers for group `bgid`
+	///
+	/// `ring` needs to be page aligned and stay valid until the ring is
+	/// unregistered; `entries` must be a power of 2. Fails with `EINVAL`
+	/// on kernels before 5.19.
+	pub unsafe fn register_buf_ring(&mut self, ring: *mut BufferRingEntry, entries: u32, bgid: u16) -> io::Result<()> {
+		let arg = BufferRingRegister::new(ring as usize as u64, entries, bgid);
+		self.register(RegisterOpCode::REGISTER_PBUF_RING, &arg as *const _ as *const _, 1)?;
+		Ok(())
+	}
+
+	pub fn unregister_buf_ring(&mut self, bgid: u16) -> io::Result<()> {
+		let arg = BufferRingRegister::new(0, 0, bgid);
+		unsafe {
+			self.register(RegisterOpCode::UNREGISTER_PBUF_RING, &arg as *const _ as *const _, 1)?;
+		}
+		Ok(())
+	}
+
+	/// new fd for the same uring
+	pub fn try_clone(&self) -> io::Result<Self> {
+		Ok(UringFile(self.0.try_clone()?))
+	}
+
+	/// query opcodes supported by the kernel
+	///
+	/// fails with `EINVAL` on kernels without `IORING_REGISTER_PROBE`
+	/// (before 5.6).
+	pub fn probe(&self) -> io::Result<Probe> {
+		Probe::new(self)
+	}
+
+	/// returns the (non-negative) syscall result; its meaning depends
+	/// on the opcode
+	pub unsafe fn register(&self, opcode: RegisterOpCode, arg: *const libc::c_void, nr_args: u32) -> io::Result<u32> {
+		let res = io_uring_register(self.as_raw_fd(), opcode.0, arg, nr_args);
+		if res < 0 {
 			Err(io::Error::last_os_error())
 		} else {
-			Ok(())
+			Ok(res as u32)
 		}
 	}
 }
@@ -26,6 +26,26 @@ impl MappedMemory {
 		}
 	}
 
+	/// private zero-initialized memory
+	pub fn anonymous(len: usize) -> io::Result<Self> {
+		let addr = unsafe { libc::mmap(
+			0 as *mut _,
+			len,
+			libc::PROT_READ | libc::PROT_WRITE,
+			libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+			-1,
+			0,
+		) };
+		if addr == libc::MAP_FAILED {
+			Err(io::Error::last_os_error())
+		} else {
+			Ok(MappedMemory {
+				addr,
+				len,
+			})
+		}
+	}
+
 	pub fn as_mut_ptr(&self) -> *mut libc::c_void {
 		self.addr
 	}
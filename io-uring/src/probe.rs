@@ -0,0 +1,66 @@
+use std::io;
+
+use crate::{
+	Operation,
+	ProbeHeader,
+	ProbeOperation,
+	ProbeOperationFlags,
+	RawOperation,
+	RegisterOpCode,
+	UringFile,
+};
+
+// opcodes are u8, so the kernel can't report more than this
+const MAX_OPS: usize = 256;
+
+#[repr(C)]
+struct ProbeBuffer {
+	header: ProbeHeader,
+	ops: [ProbeOperation; MAX_OPS],
+}
+
+/// Opcodes supported by the kernel (`IORING_REGISTER_PROBE`)
+#[derive(Clone, Debug)]
+pub struct Probe {
+	last_op: RawOperation,
+	ops: Vec<ProbeOperation>,
+}
+
+impl Probe {
+	pub(crate) fn new(file: &UringFile) -> io::Result<Self> {
+		// kernel requires the buffer to be zeroed
+		let mut buf = Box::new(ProbeBuffer {
+			header: ProbeHeader::default(),
+			ops: [ProbeOperation::default(); MAX_OPS],
+		});
+		unsafe {
+			file.register(RegisterOpCode::REGISTER_PROBE, &mut *buf as *mut ProbeBuffer as *const _, MAX_OPS as u32)?;
+		}
+		let ops_len = std::cmp::min(buf.header.ops_len as usize, MAX_OPS);
+		Ok(Probe {
+			last_op: buf.header.last_op,
+			ops: buf.ops[..ops_len].to_vec(),
+		})
+	}
+
+	/// last opcode known to the kernel (might not be supported)
+	pub fn last_op(&self) -> RawOperation {
+		self.last_op
+	}
+
+	/// per opcode entries as returned by the kernel
+	pub fn operations(&self) -> &[ProbeOperation] {
+		&self.ops
+	}
+
+	pub fn supports_raw(&self, op: RawOperation) -> bool {
+		match self.ops.get(op.0 as usize) {
+			Some(entry) => entry.flags.contains(ProbeOperationFlags::SUPPORTED),
+			None => false,
+		}
+	}
+
+	pub fn supports(&self, op: Operation) -> bool {
+		self.supports_raw(op.into())
+	}
+}
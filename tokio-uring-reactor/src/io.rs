@@ -1,15 +1,64 @@
 use std::os::unix::io::{AsRawFd, RawFd};
 use std::rc::Rc;
+use std::time::Duration;
 
 use crate::reactor::{
 	Handle,
 	AsyncRead,
+	AsyncRecv,
 	AsyncWrite,
+	BufferPool,
 };
 
+/// Files for fixed buffer operations (`Handle::async_read_fixed`,
+/// `Handle::async_write_fixed`): either a registered slot or a plain
+/// fd.
+pub trait AsFileDescriptor {
+	fn as_file_descriptor(&self) -> io_uring::FileDescriptor;
+}
+
+impl AsFileDescriptor for io_uring::FixedFile {
+	fn as_file_descriptor(&self) -> io_uring::FileDescriptor {
+		self.fd()
+	}
+}
+
+impl AsFileDescriptor for std::fs::File {
+	fn as_file_descriptor(&self) -> io_uring::FileDescriptor {
+		io_uring::FileDescriptor::FD(self.as_raw_fd())
+	}
+}
+
+impl AsFileDescriptor for crate::net::TcpStream {
+	fn as_file_descriptor(&self) -> io_uring::FileDescriptor {
+		io_uring::FileDescriptor::FD(self.as_raw_fd())
+	}
+}
+
+impl<T: AsFileDescriptor> AsFileDescriptor for Rc<T> {
+	fn as_file_descriptor(&self) -> io_uring::FileDescriptor {
+		(**self).as_file_descriptor()
+	}
+}
+
 pub trait SocketRead: AsRawFd + Sized {
 	fn read<T: AsMut<[u8]>>(self, handle: &Handle, buf: T) -> AsyncRead<T, Self> {
-		handle.async_read(self, 0, buf)
+		handle.async_read(self, 0, buf, None)
+	}
+
+	/// Read canceled by the kernel if it doesn't complete within
+	/// `timeout`
+	fn read_timeout<T: AsMut<[u8]>>(self, handle: &Handle, buf: T, timeout: Duration) -> AsyncRead<T, Self> {
+		handle.async_read(self, 0, buf, Some(timeout))
+	}
+
+	/// Stream of received chunks in buffers picked from `pool`, driven
+	/// by a single multishot recv (see `Handle::async_recv_multishot`)
+	fn recv_stream(self, pool: &BufferPool) -> AsyncRecv<Self>
+	where
+		Self: 'static,
+	{
+		pool.handle().async_recv_multishot(self, pool)
 	}
 
 	fn split(self) -> (SplitRead<Self>, SplitWrite<Self>)
@@ -22,7 +71,13 @@ pub trait SocketRead: AsRawFd + Sized {
 
 pub trait SocketWrite: AsRawFd + Sized {
 	fn write<T: AsRef<[u8]>>(self, handle: &Handle, buf: T) -> AsyncWrite<T, Self> {
-		handle.async_write(self, 0, buf)
+		handle.async_write(self, 0, buf, None)
+	}
+
+	/// Write canceled by the kernel if it doesn't complete within
+	/// `timeout`
+	fn write_timeout<T: AsRef<[u8]>>(self, handle: &Handle, buf: T, timeout: Duration) -> AsyncWrite<T, Self> {
+		handle.async_write(self, 0, buf, Some(timeout))
 	}
 }
 
@@ -8,7 +8,13 @@ pub use crate::{
 	reactor::{
 		Reactor,
 		Handle,
+		BufferPool,
+		PooledBuf,
 		Unpark,
+		Sleep,
+		Interval,
+		Timeout,
+		TimeoutError,
 	},
 };
 
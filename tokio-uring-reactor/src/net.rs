@@ -1,6 +1,7 @@
 use std::net;
 use std::io;
-use std::os::unix::io::{RawFd, AsRawFd};
+use std::os::unix::io::{RawFd, AsRawFd, FromRawFd};
+use std::time::Duration;
 
 #[cfg(feature = "nightly-async")]
 use std::{
@@ -18,12 +19,24 @@ use crate::Handle;
 pub struct TcpListener(net::TcpListener);
 
 impl TcpListener {
+	/// Stream of accepted connections
+	///
+	/// Uses a single multishot accept if the kernel supports it,
+	/// otherwise polls the listener and calls accept(2).
 	pub fn incoming(self, handle: &Handle) -> Incoming {
 		let fd = self.0.as_raw_fd();
+		let mode = if handle.supports(io_uring::Operation::Accept) {
+			IncomingMode::Multishot {
+				accept: handle.async_accept_multishot(fd),
+				accepted: false,
+			}
+		} else {
+			IncomingMode::poll(handle, fd)
+		};
 		Incoming {
 			inner: self,
-			blocked: true, // poll first
-			poll: handle.async_poll(fd, io_uring::PollFlags::IN),
+			handle: handle.clone(),
+			mode,
 		}
 	}
 }
@@ -35,12 +48,63 @@ impl From<net::TcpListener> for TcpListener {
 	}
 }
 
+#[derive(Debug)]
+enum IncomingMode {
+	Multishot {
+		accept: crate::reactor::AsyncAccept,
+		// whether multishot accept worked at least once
+		accepted: bool,
+	},
+	// kernel without (multishot) accept
+	Poll {
+		blocked: bool,
+		poll: crate::reactor::AsyncPoll,
+	},
+}
+
+impl IncomingMode {
+	fn poll(handle: &Handle, fd: RawFd) -> Self {
+		IncomingMode::Poll {
+			blocked: true, // poll first
+			poll: handle.async_poll(fd, io_uring::PollFlags::IN),
+		}
+	}
+}
+
 #[must_use = "streams do nothing unless polled"]
 #[derive(Debug)]
 pub struct Incoming {
 	inner: TcpListener,
-	blocked: bool,
-	poll: crate::reactor::AsyncPoll,
+	handle: Handle,
+	mode: IncomingMode,
+}
+
+impl Incoming {
+	// None if the client already reset the connection
+	fn accepted(fd: RawFd) -> io::Result<Option<(TcpStream, net::SocketAddr)>> {
+		let stream = unsafe { net::TcpStream::from_raw_fd(fd) };
+		match stream.peer_addr() {
+			Ok(addr) => Ok(Some((TcpStream(stream), addr))),
+			Err(ref e) if e.raw_os_error() == Some(libc::ENOTCONN) => {
+				log::debug!("accepted connection already closed");
+				Ok(None)
+			},
+			Err(e) => Err(e),
+		}
+	}
+
+	// multishot accept isn't supported before 5.19; the accept opcode
+	// itself exists since 5.5
+	fn fallback(&mut self, e: &io::Error) -> bool {
+		match self.mode {
+			IncomingMode::Multishot { accepted: false, .. } if e.raw_os_error() == Some(libc::EINVAL) => {
+				log::debug!("multishot accept failed, falling back to poll: {}", e);
+				self.mode = IncomingMode::poll(&self.handle, self.inner.0.as_raw_fd());
+				true
+			},
+			_ => false,
+		}
+	}
 }
 
 impl futures::Stream for Incoming {
@@ -49,31 +113,53 @@ impl futures::Stream for Incoming {
 
 	fn poll(&mut self) -> futures::Poll<Option<Self::Item>, Self::Error> {
 		loop {
-			if !self.blocked {
-				match self.inner.0.accept() {
-					Ok((s, a)) => return Ok(futures::Async::Ready(Some((
-						TcpStream(s),
-						a,
-					)))),
-					Err(e) => {
-						if e.kind() == io::ErrorKind::Interrupted {
-							continue; // again
-						} else if e.kind() == io::ErrorKind::WouldBlock {
-							self.blocked = true;
-						} else {
-							return Err(e);
+			let e = match self.mode {
+				IncomingMode::Multishot { ref mut accept, ref mut accepted } => {
+					match accept.poll() {
+						Ok(futures::Async::NotReady) => return Ok(futures::Async::NotReady),
+						Ok(futures::Async::Ready(None)) => unreachable!(),
+						Ok(futures::Async::Ready(Some(fd))) => {
+							*accepted = true;
+							match Self::accepted(fd)? {
+								Some(item) => return Ok(futures::Async::Ready(Some(item))),
+								None => continue,
+							}
+						},
+						Err(e) => e,
+					}
+				},
+				IncomingMode::Poll { ref mut blocked, ref mut poll } => {
+					if !*blocked {
+						match self.inner.0.accept() {
+							Ok((s, a)) => return Ok(futures::Async::Ready(Some((
+								TcpStream(s),
+								a,
+							)))),
+							Err(e) => {
+								if e.kind() == io::ErrorKind::Interrupted {
+									continue; // again
+								} else if e.kind() == io::ErrorKind::WouldBlock {
+									*blocked = true;
+								} else {
+									return Err(e);
+								}
+							}
 						}
 					}
-				}
-			}
-			match self.poll.poll()? {
-				futures::Async::NotReady => return Ok(futures::Async::NotReady),
-				futures::Async::Ready(None) => unreachable!(),
-				futures::Async::Ready(Some(_events)) => {
-					// println!("Incoming events: {:?}", _events);
-					self.blocked = false;
-					// try loop again
+					match poll.poll()? {
+						futures::Async::NotReady => return Ok(futures::Async::NotReady),
+						futures::Async::Ready(None) => unreachable!(),
+						futures::Async::Ready(Some(_events)) => {
+							// println!("Incoming events: {:?}", _events);
+							*blocked = false;
+							// try loop again
+							continue;
+						},
+					}
 				},
+			};
+			if !self.fallback(&e) {
+				return Err(e);
 			}
 		}
 	}
@@ -84,31 +170,55 @@ impl futures_core::Stream for Incoming {
 	type Item = io::Result<(TcpStream, net::SocketAddr)>;
 
 	fn poll_next(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this: &mut Self = &mut *self;
 		loop {
-			if !self.blocked {
-				match self.inner.0.accept() {
-					Ok((s, a)) => return Poll::Ready(Some(Ok((
-						TcpStream(s),
-						a,
-					)))),
-					Err(e) => {
-						if e.kind() == io::ErrorKind::Interrupted {
-							continue; // again
-						} else if e.kind() == io::ErrorKind::WouldBlock {
-							self.blocked = true;
-						} else {
-							return Poll::Ready(Some(Err(e)));
+			let e = match this.mode {
+				IncomingMode::Multishot { ref mut accept, ref mut accepted } => {
+					match Pin::new(accept).poll_next(ctx) {
+						Poll::Pending => return Poll::Pending,
+						Poll::Ready(None) => unreachable!(),
+						Poll::Ready(Some(Ok(fd))) => {
+							*accepted = true;
+							match Self::accepted(fd) {
+								Ok(Some(item)) => return Poll::Ready(Some(Ok(item))),
+								Ok(None) => continue,
+								Err(e) => return Poll::Ready(Some(Err(e))),
+							}
+						},
+						Poll::Ready(Some(Err(e))) => e,
+					}
+				},
+				IncomingMode::Poll { ref mut blocked, ref mut poll } => {
+					if !*blocked {
+						match this.inner.0.accept() {
+							Ok((s, a)) => return Poll::Ready(Some(Ok((
+								TcpStream(s),
+								a,
+							)))),
+							Err(e) => {
+								if e.kind() == io::ErrorKind::Interrupted {
+									continue; // again
+								} else if e.kind() == io::ErrorKind::WouldBlock {
+									*blocked = true;
+								} else {
+									return Poll::Ready(Some(Err(e)));
+								}
+							}
 						}
 					}
-				}
-			}
-			match unsafe { Pin::new_unchecked(&mut self.poll) }.poll(ctx)? {
-				Poll::Pending => return Poll::Pending,
-				Poll::Ready(_events) => {
-					// println!("Incoming events: {:?}", _events);
-					self.blocked = false;
-					// try loop again
+					match unsafe { Pin::new_unchecked(poll) }.poll(ctx)? {
+						Poll::Pending => return Poll::Pending,
+						Poll::Ready(_events) => {
+							// println!("Incoming events: {:?}", _events);
+							*blocked = false;
+							// try loop again
+							continue;
+						},
+					}
 				},
+			};
+			if !this.fallback(&e) {
+				return Poll::Ready(Some(Err(e)));
 			}
 		}
 	}
@@ -117,6 +227,39 @@ impl futures_core::Stream for Incoming {
 #[derive(Debug)]
 pub struct TcpStream(net::TcpStream);
 
+impl TcpStream {
+	/// Connect to `addr` (`IORING_OP_CONNECT` on a new non-blocking
+	/// socket)
+	pub fn connect(handle: &Handle, addr: &net::SocketAddr) -> ConnectFuture {
+		Self::connect_with(handle, addr, None)
+	}
+
+	/// Like `connect`, but fails with `io::ErrorKind::TimedOut` if the
+	/// connection isn't established within `timeout`
+	pub fn connect_timeout(handle: &Handle, addr: &net::SocketAddr, timeout: Duration) -> ConnectFuture {
+		Self::connect_with(handle, addr, Some(timeout))
+	}
+
+	fn connect_with(handle: &Handle, addr: &net::SocketAddr, timeout: Option<Duration>) -> ConnectFuture {
+		match tcp_socket(addr) {
+			Ok(socket) => ConnectFuture(ConnectState::Connecting(handle.async_connect(socket, addr, timeout))),
+			Err(e) => ConnectFuture(ConnectState::Failed(e)),
+		}
+	}
+}
+
+fn tcp_socket(addr: &net::SocketAddr) -> io::Result<net::TcpStream> {
+	let domain = match addr {
+		net::SocketAddr::V4(_) => libc::AF_INET,
+		net::SocketAddr::V6(_) => libc::AF_INET6,
+	};
+	let fd = unsafe { libc::socket(domain, libc::SOCK_STREAM | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC, 0) };
+	if fd < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	Ok(unsafe { net::TcpStream::from_raw_fd(fd) })
+}
+
 impl AsRawFd for TcpStream {
 	fn as_raw_fd(&self) -> RawFd {
 		self.0.as_raw_fd()
@@ -125,3 +268,73 @@ impl AsRawFd for TcpStream {
 
 impl crate::io::SocketRead for TcpStream {}
 impl crate::io::SocketWrite for TcpStream {}
+
+#[derive(Debug)]
+enum ConnectState {
+	Connecting(crate::reactor::AsyncConnect<net::TcpStream>),
+	// socket creation failed
+	Failed(io::Error),
+	Closed,
+}
+
+/// Future resolving to a connected `TcpStream`
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct ConnectFuture(ConnectState);
+
+impl futures::Future for ConnectFuture {
+	type Item = TcpStream;
+	type Error = io::Error;
+
+	fn poll(&mut self) -> futures::Poll<Self::Item, Self::Error> {
+		match self.0 {
+			ConnectState::Connecting(ref mut connect) => {
+				match connect.poll() {
+					Ok(futures::Async::NotReady) => Ok(futures::Async::NotReady),
+					Ok(futures::Async::Ready(s)) => {
+						self.0 = ConnectState::Closed;
+						Ok(futures::Async::Ready(TcpStream(s)))
+					},
+					Err(e) => {
+						self.0 = ConnectState::Closed;
+						Err(e.error)
+					},
+				}
+			},
+			_ => {
+				match std::mem::replace(&mut self.0, ConnectState::Closed) {
+					ConnectState::Connecting(_) => unreachable!(),
+					ConnectState::Failed(e) => Err(e),
+					ConnectState::Closed => panic!("already finished"),
+				}
+			}
+		}
+	}
+}
+
+#[cfg(feature = "nightly-async")]
+impl Future for ConnectFuture {
+	type Output = io::Result<TcpStream>;
+
+	fn poll(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
+		let this: &mut Self = &mut *self;
+		match this.0 {
+			ConnectState::Connecting(ref mut connect) => {
+				match Pin::new(connect).poll(ctx) {
+					Poll::Pending => Poll::Pending,
+					Poll::Ready(r) => {
+						this.0 = ConnectState::Closed;
+						Poll::Ready(r.map(TcpStream).map_err(|e| e.error))
+					},
+				}
+			},
+			_ => {
+				match std::mem::replace(&mut this.0, ConnectState::Closed) {
+					ConnectState::Connecting(_) => unreachable!(),
+					ConnectState::Failed(e) => Poll::Ready(Err(e)),
+					ConnectState::Closed => panic!("already finished"),
+				}
+			}
+		}
+	}
+}
@@ -1,29 +1,55 @@
+mod async_accept;
+mod async_connect;
 mod async_poll;
 mod async_read;
+mod async_read_pooled;
+mod async_recv;
+mod async_splice;
 mod async_write;
+mod buffer_pool;
+mod timer;
 
 use std::{
 	cell::UnsafeCell,
+	collections::HashSet,
 	convert::Infallible,
 	fmt,
 	io,
+	net::SocketAddr,
 	os::unix::io::{RawFd, AsRawFd},
 	pin::Pin,
 	rc::{Rc, Weak},
-	time::Duration,
+	time::{Duration, Instant},
 };
 
 use crate::{
+	io::AsFileDescriptor,
 	registration::{
 		RawRegistration,
+		Registration,
 		UringResult,
 	},
 	unpark,
 };
 
+pub use self::async_accept::AsyncAccept;
+pub use self::async_connect::AsyncConnect;
 pub use self::async_poll::AsyncPoll;
 pub use self::async_read::AsyncRead;
+pub use self::async_read_pooled::AsyncReadPooled;
+pub use self::async_recv::AsyncRecv;
+pub use self::async_splice::AsyncSplice;
 pub use self::async_write::AsyncWrite;
+pub use self::buffer_pool::{
+	BufferPool,
+	PooledBuf,
+};
+pub use self::timer::{
+	Interval,
+	Sleep,
+	Timeout,
+	TimeoutError,
+};
 
 fn iovec_from(data: &[u8]) -> libc::iovec {
 	libc::iovec {
@@ -32,6 +58,10 @@ fn iovec_from(data: &[u8]) -> libc::iovec {
 	}
 }
 
+// size of the fixed file table (created on first `Handle::register_file`);
+// the kernel limits it to RLIMIT_NOFILE
+const FIXED_FILE_SLOTS: u32 = 1024;
+
 fn iovec_empty() -> libc::iovec {
 	libc::iovec {
 		iov_base: 0 as *mut libc::c_void,
@@ -43,6 +73,50 @@ fn sq_full_map_err(_error: io_uring::SubmissionError<Infallible>) -> io::Error {
 	io::Error::new(io::ErrorKind::Other, "submission queue full")
 }
 
+// submit `fill` as one entry for `reg`, followed by a linked timeout if
+// `timeout` is set; either both get queued or none.
+//
+// timeout needs to live until the entry was submitted.
+fn submit_with_link_timeout<F>(sq: &mut io_uring::SubmissionQueue, state: &mut CompletionState, timeout: Option<*const io_uring::KernelTimespec>, reg: RawRegistration, fill: F) -> io::Result<()>
+where
+	F: FnOnce(&mut io_uring::SubmissionEntry),
+{
+	let user_data = reg.user_data();
+	let mut bulk = sq.bulk();
+	match timeout {
+		None => bulk.submit_with(|entry| {
+			fill(entry);
+			entry.user_data = unsafe { reg.into_user_data() };
+			Ok(())
+		}),
+		Some(timeout) => {
+			let mut fill = Some(fill);
+			bulk.submit_chain_with(2, |i, entry| {
+				if 0 == i {
+					(fill.take().expect("fill only once"))(entry);
+					entry.user_data = reg.user_data();
+				} else {
+					unsafe {
+						entry.link_timeout(timeout, io_uring::TimeoutFlags::default());
+						// tells the registration whether the timeout expired
+						entry.user_data = reg.link_timeout_user_data();
+					}
+				}
+				Ok(())
+			}).map(|()| {
+				// the operation entry keeps the reference of `reg`
+				std::mem::forget(reg);
+			})
+		},
+	}.map_err(sq_full_map_err)?;
+	state.started(user_data);
+	if timeout.is_some() {
+		// the linked timeout completes separately
+		state.active_wait += 1;
+	}
+	Ok(())
+}
+
 pub struct Unpark(unpark::Unpark);
 
 impl tokio_executor::park::Unpark for Unpark {
@@ -53,10 +127,14 @@ impl tokio_executor::park::Unpark for Unpark {
 
 // stuff we need to mutate during uring completion handling
 struct CompletionState {
-	requeue_timer: bool,
-	timer_pending: bool,
+	// park timeout (`TIMER`) submitted and not completed yet
+	timer_active: bool,
 	requeue_park: bool,
 	active_wait: usize,
+	// user_data of active operations with a registration
+	active: HashSet<u64>,
+	// kernel rejected `CANCEL_ALL` (needs IORING_ASYNC_CANCEL_ANY)
+	cancel_all_failed: bool,
 	park: unpark::Park,
 }
 
@@ -64,64 +142,144 @@ impl CompletionState {
 	// special events must be "odd"
 	const TIMER: u64 = 0x1;
 	const PARK: u64 = 0x3;
+	const CANCEL_ALL: u64 = 0x5;
 
 	fn new() -> io::Result<Self> {
 		Ok(CompletionState {
-			requeue_timer: true,
-			timer_pending: false,
+			timer_active: false,
 			requeue_park: true,
 			active_wait: 0,
+			active: HashSet::new(),
+			cancel_all_failed: false,
 			park: unpark::Park::new()?,
 		})
 	}
 
+	// operation with registration `user_data` was submitted
+	fn started(&mut self, user_data: u64) {
+		self.active_wait += 1;
+		self.active.insert(user_data);
+	}
+
+	// returns true if at least one completion was received
+	fn reap(&mut self, cq: &mut io_uring::CompletionQueue) -> bool {
+		let mut received_completion: bool = false;
+
+		for cqe in cq.into_iter() {
+			received_completion = true;
+
+			let result = UringResult {
+				result: cqe.res,
+				flags: cqe.flags,
+			};
+
+			self.handle_completion(cqe.user_data, result);
+		}
+
+		received_completion
+	}
+
 	fn handle_completion(&mut self, user_data: u64, result: UringResult) {
 		if 0 == user_data {
 			// fire-and-forget command (POLL_DEL)
 			return;
 		}
+		if result.has_more() {
+			// multishot operation stays active; the kernel keeps using
+			// the registration
+			assert!(0 == user_data & 0x1, "multishot internal event: {}", user_data);
+			let mut reg = unsafe { RawRegistration::from_user_data(user_data) };
+			reg.notify(result);
+			std::mem::forget(reg);
+			return;
+		}
 		self.active_wait -= 1;
 		if 0 == user_data & 0x1 {
+			self.active.remove(&user_data);
 			let mut reg = unsafe { RawRegistration::from_user_data(user_data) };
 			reg.notify(result);
 		} else {
 			match user_data {
 				CompletionState::TIMER => {
-					// wakeup by timer, just requeue read and rearm/disable next turn
-					self.requeue_timer = true;
-					self.timer_pending = true;
+					// wakeup by timer (or timer was removed); rearm next turn if needed
+					self.timer_active = false;
 				},
 				CompletionState::PARK => {
 					// wakeup by park, just requeue read
-					self.park.clear_event();
+					if result.result >= 0 {
+						// not canceled, pipe is readable
+						self.park.clear_event();
+					}
 					self.requeue_park = true;
 				},
-				_ => panic!("unknown event: {}", user_data),
+				CompletionState::CANCEL_ALL => {
+					if result.result == -libc::EINVAL {
+						self.cancel_all_failed = true;
+					}
+				},
+				_ => {
+					// linked timeout of a registration (see
+					// `RawRegistration::link_timeout_user_data`)
+					let mut reg = unsafe { RawRegistration::from_user_data(user_data & !0x1) };
+					reg.notify_link_timeout(result);
+				},
 			}
 		}
 	}
 }
 
 struct Inner {
-	// FIXME: on shutdown need to clear (wait for completion!) *at
-	// least* internal operations before freeing memory
+	// the kernel might still reference memory owned by the reactor
+	// while operations are active; `Reactor` drop leaks everything if
+	// `shutdown` fails to wait for all of them.
 	uring: io_uring::Uring,
+	// don't accept new operations after shutdown started
+	is_shutdown: bool,
 	completion_state: CompletionState,
-	timerfd: timerfd::TimerFd,
+	// park timeout; must not move while a `TIMER` submission is queued
+	// (kernel reads it when the entry gets submitted)
+	timer_timespec: io_uring::KernelTimespec,
 	read_buf: [u8; 32], // for various wakeup mechanisms
 	read_iovec: [libc::iovec; 1],
+	// None if the kernel doesn't support probing
+	probe: Option<io_uring::Probe>,
+	// registered file table; created on first `Handle::register_file`
+	fixed_files: Option<io_uring::FixedFiles>,
+	// whether buffers from `Handle::register_buffers` are registered
+	fixed_buffers: bool,
+	// group id for the next `BufferPool`
+	next_buffer_group: u16,
+	// provided buffer groups of dropped pools to remove
+	retired_buffer_groups: buffer_pool::RetiredGroups,
+	// last seen value of the kernel completion queue overflow counter
+	cq_overflow: u32,
+	// completions the kernel had to drop; the operations they belong to
+	// will never finish
+	lost_completions: u64,
 }
 
 impl Inner {
 	fn build() -> io::Result<Self> {
-		let params = io_uring::SetupParameters::new(io_uring::SetupFlags::default());
-
+		let mut uring = io_uring::UringBuilder::new(4096).build()?;
+		let probe = uring.file().probe().ok();
+		if !uring.features().contains(io_uring::Features::NODROP) {
+			log::debug!("kernel drops completions if the completion queue is full");
+		}
+		let cq_overflow = uring.completion_queue().overflow();
 		Ok(Inner {
-			uring: io_uring::Uring::new(4096, params)?,
+			uring,
+			is_shutdown: false,
 			completion_state: CompletionState::new()?,
-			timerfd: timerfd::TimerFd::new()?,
+			timer_timespec: io_uring::KernelTimespec::default(),
 			read_buf: [0u8; 32],
 			read_iovec: [ iovec_empty() ],
+			probe,
+			fixed_files: None,
+			fixed_buffers: false,
+			next_buffer_group: 0,
+			retired_buffer_groups: buffer_pool::RetiredGroups::default(),
+			cq_overflow,
+			lost_completions: 0,
 		})
 	}
 
@@ -130,58 +288,86 @@ impl Inner {
 		self.as_mut().read_iovec[0] = iovec;
 	}
 
+	fn supports(&self, op: io_uring::Operation) -> bool {
+		match self.probe {
+			Some(ref probe) => probe.supports(op),
+			None => false,
+		}
+	}
+
 	// returns true if at least one completion was received
-	fn check_completions(&mut self) -> bool {
-		let mut received_completion: bool = false;
+	//
+	// fails if the kernel dropped completions
+	fn check_completions(&mut self) -> io::Result<bool> {
+		let mut received_completion = self.reap_completions();
 
-		for cqe in self.uring.completion_queue().into_iter() {
+		// with `Features::NODROP` the kernel keeps completions that
+		// didn't fit; flush them into the (now empty) queue
+		while self.uring.submission_queue().flags().contains(io_uring::SubmissionQueueFlags::CQ_OVERFLOW) {
+			log::debug!("completion queue overflow, flushing");
+			self.uring.flush_overflow()?;
+			if !self.reap_completions() {
+				break;
+			}
 			received_completion = true;
+		}
 
-			let result = UringResult {
-				result: cqe.res,
-				flags: cqe.flags,
-			};
-
-			self.completion_state.handle_completion(cqe.user_data, result);
+		// without `Features::NODROP` (or if the kernel couldn't store
+		// them) completions are lost; fail instead of waiting forever.
+		let overflow = self.uring.completion_queue().overflow();
+		let lost = overflow.wrapping_sub(self.cq_overflow);
+		if 0 != lost {
+			self.cq_overflow = overflow;
+			self.lost_completions += lost as u64;
+			log::error!("completion queue overflow: {} completions lost", lost);
+			return Err(io::Error::new(
+				io::ErrorKind::Other,
+				format!("uring completion queue overflow: {} completions lost", lost),
+			));
 		}
 
-		received_completion
+		Ok(received_completion)
+	}
+
+	// returns true if at least one completion was received
+	fn reap_completions(&mut self) -> bool {
+		self.completion_state.reap(self.uring.completion_queue())
 	}
 
 	fn park_inner(&mut self, mut wait: bool, timeout: Option<Duration>) -> io::Result<()> {
-		if self.check_completions() {
+		if self.check_completions()? {
 			// don't wait for new events below; we first need to handle this one
 			wait = false;
 		}
 
+		let retired = self.retired_buffer_groups.clone();
+		retired.remove_all(self);
+
 		// proper check later, but don't need to setup various things if
 		// we already know we're not going to wait
 		if self.completion_state.park.pending() {
 			wait = false;
 		}
 
+		// timeout passed to io_uring_enter directly (`Features::EXT_ARG`)
+		let mut enter_timeout = None;
+
 		if wait {
-			// set timer before we requeue it
 			if let Some(timeout) = timeout {
 				log::trace!("wait with timeout: {:?}", timeout);
 				debug_assert!(timeout != Duration::new(0, 0)); // "zero" timer must trigger wait = false
-				self.timerfd.set_state(timerfd::TimerState::Oneshot(timeout), timerfd::SetTimeFlags::Default);
-				self.completion_state.timer_pending = false;
-			} else {
-				log::trace!("wait without timeout");
-				if self.completion_state.timer_pending {
-					// disarm timer after it triggered (instead of reading it)
-					self.timerfd.set_state(timerfd::TimerState::Disarmed, timerfd::SetTimeFlags::Default);
-					self.completion_state.timer_pending = false;
-				}
-			}
-
-			if self.completion_state.requeue_timer {
-				if self.queue_timer_poll().is_err() {
+				if self.uring.features().contains(io_uring::Features::EXT_ARG) {
+					enter_timeout = Some(timeout);
+				} else if self.queue_timer(timeout).is_err() {
 					// never wait if submission queue is full and we couldn't insert timer
 					wait = false;
-				} else {
-					self.completion_state.requeue_timer = false;
+				}
+			} else {
+				log::trace!("wait without timeout");
+				if self.completion_state.timer_active {
+					// remove old timer; if this fails we just get a
+					// spurious wakeup later.
+					let _ = self.queue_timer_remove();
 				}
 			}
 		}
@@ -197,7 +383,10 @@ impl Inner {
 
 		let pending = self.uring.submission_queue().pending_submissions();
 
-		{
+		// completions reaped while submitting; handled after leaving park
+		// (which borrows the completion state)
+		let mut reaped = Vec::new();
+		let status = {
 			let park_enter = self.completion_state.park.enter();
 			if !park_enter.allow_wait {
 				wait = false;
@@ -208,23 +397,38 @@ impl Inner {
 				return Ok(());
 			}
 
-			let (min_complete, flags) = if wait {
-				(1, io_uring::EnterFlags::GETEVENTS)
-			} else {
-				// submit only
-				(0, io_uring::EnterFlags::default())
-			};
+			// 0: submit only
+			let min_complete = if wait { 1 } else { 0 };
 
 			log::trace!(
-				"io_uring_enter: (to_submit = {}, min_complete = {}, flags = {:?}, sig = None)",
+				"io_uring_enter: (to_submit = {}, min_complete = {}, timeout = {:?})",
 				pending,
 				min_complete,
-				flags,
+				enter_timeout,
 			);
-			self.uring.file().enter(pending, min_complete, flags, None)?;
+			let reap = |cq: &mut io_uring::CompletionQueue| {
+				let before = reaped.len();
+				reaped.extend(cq.into_iter().map(|cqe| (cqe.user_data, UringResult {
+					result: cqe.res,
+					flags: cqe.flags,
+				})));
+				reaped.len() != before
+			};
+			match enter_timeout {
+				Some(timeout) if wait => self.uring.submit_and_wait_timeout(min_complete, timeout, reap),
+				_ => self.uring.submit_and_wait(min_complete, reap),
+			}
+		};
+		for (user_data, result) in reaped {
+			self.completion_state.handle_completion(user_data, result);
+		}
+		let status = status?;
+		if 0 != status.staged {
+			// submitted on next park
+			log::debug!("io_uring_enter: submitted {}, {} still staged", status.submitted, status.staged);
 		}
 
-		self.check_completions();
+		self.check_completions()?;
 
 		Ok(())
 	}
@@ -242,20 +446,156 @@ impl Inner {
 		}
 	}
 
-	fn queue_timer_poll(&mut self) -> Result<(), io_uring::SubmissionError<Infallible>> {
-		let fd = self.timerfd.as_raw_fd();
+	// cancel all active operations and wait for their completions
+	fn shutdown(&mut self, timeout: Duration) -> io::Result<()> {
+		let deadline = Instant::now() + timeout;
+
+		if !self.is_shutdown {
+			self.is_shutdown = true;
+			self.queue_shutdown_cancel();
+		}
+
+		loop {
+			if 0 != self.uring.submission_queue().pending_submissions() {
+				let state = &mut self.completion_state;
+				self.uring.submit_and_wait(0, |cq| state.reap(cq))?;
+			}
+			self.check_completions()?;
+
+			if self.completion_state.cancel_all_failed {
+				self.completion_state.cancel_all_failed = false;
+				log::debug!("reactor shutdown: kernel can't cancel all, canceling one by one");
+				self.queue_cancel_each();
+				continue;
+			}
+
+			let active = self.completion_state.active_wait;
+			if 0 == active {
+				log::debug!("reactor shutdown complete");
+				return Ok(());
+			}
+
+			let now = Instant::now();
+			if now >= deadline {
+				return Err(io::Error::new(
+					io::ErrorKind::TimedOut,
+					format!("reactor shutdown: {} operations didn't complete in time", active),
+				));
+			}
+			log::debug!("reactor shutdown: waiting for {} operations", active);
+			self.wait_completions(deadline - now)?;
+		}
+	}
+
+	fn queue_shutdown_cancel(&mut self) {
+		// internal operations never complete on their own
+		if self.completion_state.timer_active && self.queue_timer_remove().is_err() {
+			log::warn!("reactor shutdown: couldn't remove timer");
+		}
+		if !self.completion_state.requeue_park && self.queue_park_remove().is_err() {
+			log::warn!("reactor shutdown: couldn't remove park poll");
+		}
+		// everything else; kernels without IORING_ASYNC_CANCEL_ANY reject
+		// this, and the operations are canceled one by one instead
+		if self.queue_cancel_all().is_err() {
+			log::debug!("reactor shutdown: couldn't queue cancel all, canceling one by one");
+			self.queue_cancel_each();
+		}
+	}
+
+	fn queue_park_remove(&mut self) -> Result<(), io_uring::SubmissionError<Infallible>> {
 		self.uring.submission_queue().bulk().submit_with(|entry| {
-			entry.poll_add(
-				io_uring::FileDescriptor::FD(fd),
-				io_uring::PollFlags::IN,
-			);
-			entry.user_data = CompletionState::TIMER;
+			entry.poll_remove(CompletionState::PARK);
+			entry.user_data = 0; // fire-and-forget
+			Ok(())
+		})
+	}
+
+	fn queue_cancel_all(&mut self) -> Result<(), io_uring::SubmissionError<Infallible>> {
+		self.uring.submission_queue().bulk().submit_with(|entry| {
+			entry.async_cancel(0, io_uring::CancelFlags::ANY | io_uring::CancelFlags::ALL);
+			entry.user_data = CompletionState::CANCEL_ALL;
 			Ok(())
 		})?;
 		self.completion_state.active_wait += 1;
 		Ok(())
 	}
 
+	// cancel active operations one by one (kernel before 5.19)
+	fn queue_cancel_each(&mut self) {
+		let active: Vec<u64> = self.completion_state.active.iter().cloned().collect();
+		for user_data in active {
+			if self.queue_cancel(user_data).is_ok() {
+				continue;
+			}
+			// submission queue full; make room and try again
+			let state = &mut self.completion_state;
+			if let Err(e) = self.uring.submit_and_wait(0, |cq| state.reap(cq)).and_then(|_| self.queue_cancel(user_data)) {
+				log::warn!("reactor shutdown: couldn't cancel operation: {}", e);
+			}
+		}
+	}
+
+	// wait until the completion queue isn't empty (or timeout)
+	fn wait_completions(&mut self, timeout: Duration) -> io::Result<()> {
+		if self.uring.features().contains(io_uring::Features::EXT_ARG) {
+			let state = &mut self.completion_state;
+			self.uring.submit_and_wait_timeout(1, timeout, |cq| state.reap(cq))?;
+			return Ok(());
+		}
+
+		let mut pollfd = libc::pollfd {
+			fd: self.uring.file().as_raw_fd(),
+			events: libc::POLLIN,
+			revents: 0,
+		};
+		// round up to full milliseconds
+		let timeout_ms = (timeout.as_micros() + 999) / 1000;
+		let timeout_ms = std::cmp::min(timeout_ms, libc::c_int::max_value() as u128) as libc::c_int;
+		if unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } < 0 {
+			let e = io::Error::last_os_error();
+			if e.kind() != io::ErrorKind::Interrupted {
+				return Err(e);
+			}
+		}
+		Ok(())
+	}
+
+	// if the timer already fired (or is about to) an update fails; but
+	// then the `TIMER` completion wakes us up anyway.
+	fn queue_timer(&mut self, timeout: Duration) -> Result<(), io_uring::SubmissionError<Infallible>> {
+		self.timer_timespec = timeout.into();
+		let timespec: *const io_uring::KernelTimespec = &self.timer_timespec;
+		if self.completion_state.timer_active {
+			self.uring.submission_queue().bulk().submit_with(|entry| {
+				unsafe {
+					entry.timeout_update(CompletionState::TIMER, timespec, io_uring::TimeoutFlags::default());
+				}
+				entry.user_data = 0; // fire-and-forget
+				Ok(())
+			})?;
+		} else {
+			self.uring.submission_queue().bulk().submit_with(|entry| {
+				unsafe {
+					entry.timeout(timespec, 0, io_uring::TimeoutFlags::default());
+				}
+				entry.user_data = CompletionState::TIMER;
+				Ok(())
+			})?;
+			self.completion_state.timer_active = true;
+			self.completion_state.active_wait += 1;
+		}
+		Ok(())
+	}
+
+	fn queue_timer_remove(&mut self) -> Result<(), io_uring::SubmissionError<Infallible>> {
+		self.uring.submission_queue().bulk().submit_with(|entry| {
+			entry.timeout_remove(CompletionState::TIMER);
+			entry.user_data = 0; // fire-and-forget
+			Ok(())
+		})
+	}
+
 	fn queue_park_read(&mut self) -> Result<(), io_uring::SubmissionError<Infallible>> {
 		let fd = self.completion_state.park.as_raw_fd();
 		//let iovec = &self.read_iovec;
@@ -263,6 +603,7 @@ impl Inner {
 			entry.poll_add(
 				io_uring::FileDescriptor::FD(fd),
 				io_uring::PollFlags::IN,
+				io_uring::PollAddFlags::default(),
 			);
 
 /*
@@ -284,56 +625,236 @@ impl Inner {
 		Ok(())
 	}
 
-	fn queue_async_read(&mut self, fd: RawFd, offset: u64, iovec: *const [libc::iovec], reg: RawRegistration) -> io::Result<()> {
-		self.uring.submission_queue().bulk().submit_with(|entry| {
+	fn queue_async_read(&mut self, fd: io_uring::FileDescriptor, offset: u64, iovec: *const [libc::iovec], timeout: Option<*const io_uring::KernelTimespec>, reg: RawRegistration) -> io::Result<()> {
+		submit_with_link_timeout(self.uring.submission_queue(), &mut self.completion_state, timeout, reg, |entry| {
 			unsafe {
 				entry.readv(
 					io_uring::IoPriority::None,
-					io_uring::FileDescriptor::FD(fd),
+					fd,
 					offset,
 					io_uring::ReadWriteFlags::default(),
 					iovec,
 				);
-				entry.user_data = reg.into_user_data();
 			}
-			Ok(())
-		}).map_err(sq_full_map_err)?;
-		self.completion_state.active_wait += 1;
-		Ok(())
+		})
 	}
 
-	fn queue_async_write(&mut self, fd: RawFd, offset: u64, iovec: *const [libc::iovec], reg: RawRegistration) -> io::Result<()> {
-		self.uring.submission_queue().bulk().submit_with(|entry| {
+	fn queue_async_write(&mut self, fd: io_uring::FileDescriptor, offset: u64, iovec: *const [libc::iovec], timeout: Option<*const io_uring::KernelTimespec>, reg: RawRegistration) -> io::Result<()> {
+		submit_with_link_timeout(self.uring.submission_queue(), &mut self.completion_state, timeout, reg, |entry| {
 			unsafe {
 				entry.writev(
 					io_uring::IoPriority::None,
-					io_uring::FileDescriptor::FD(fd),
+					fd,
+					offset,
+					io_uring::ReadWriteFlags::default(),
+					iovec,
+				);
+			}
+		})
+	}
+
+	// buf must stay valid until the operation completed
+	fn queue_async_read_fixed(&mut self, fd: io_uring::FileDescriptor, offset: u64, buf: *mut io_uring::FixedBuf, timeout: Option<*const io_uring::KernelTimespec>, reg: RawRegistration) -> io::Result<()> {
+		submit_with_link_timeout(self.uring.submission_queue(), &mut self.completion_state, timeout, reg, |entry| {
+			unsafe {
+				(*buf).read_fixed(
+					entry,
+					io_uring::IoPriority::None,
+					fd,
+					offset,
+					io_uring::ReadWriteFlags::default(),
+					..,
+				);
+			}
+		})
+	}
+
+	// buf must stay valid until the operation completed
+	fn queue_async_write_fixed(&mut self, fd: io_uring::FileDescriptor, offset: u64, buf: *const io_uring::FixedBuf, len: usize, timeout: Option<*const io_uring::KernelTimespec>, reg: RawRegistration) -> io::Result<()> {
+		submit_with_link_timeout(self.uring.submission_queue(), &mut self.completion_state, timeout, reg, |entry| {
+			unsafe {
+				(*buf).write_fixed(
+					entry,
+					io_uring::IoPriority::None,
+					fd,
+					offset,
+					io_uring::ReadWriteFlags::default(),
+					..len,
+				);
+			}
+		})
+	}
+
+	// read into a buffer the kernel picks from group `bgid`; iovec only
+	// gives the maximum length
+	fn queue_async_read_select(&mut self, fd: io_uring::FileDescriptor, offset: u64, iovec: *const [libc::iovec], bgid: u16, reg: RawRegistration) -> io::Result<()> {
+		let user_data = reg.user_data();
+		self.uring.submission_queue().bulk().submit_with(|entry| {
+			unsafe {
+				entry.readv(
+					io_uring::IoPriority::None,
+					fd,
 					offset,
 					io_uring::ReadWriteFlags::default(),
 					iovec,
 				);
 				entry.user_data = reg.into_user_data();
 			}
+			entry.set_buffer_select(bgid);
 			Ok(())
 		}).map_err(sq_full_map_err)?;
-		self.completion_state.active_wait += 1;
+		self.completion_state.started(user_data);
 		Ok(())
 	}
 
-	fn queue_async_poll(&mut self, fd: RawFd, flags: io_uring::PollFlags, reg: RawRegistration) -> io::Result<()> {
+	// fire-and-forget; buffers must stay valid until they are selected
+	// or removed
+	fn queue_provide_buffers(&mut self, addr: *mut u8, len: u32, nbufs: u32, bgid: u16, bid: u16) -> io::Result<()> {
+		self.uring.submission_queue().bulk().submit_with(|entry| {
+			unsafe {
+				entry.provide_buffers(addr, len, nbufs, bgid, bid);
+			}
+			entry.user_data = 0;
+			Ok(())
+		}).map_err(sq_full_map_err)
+	}
+
+	// fire-and-forget
+	fn queue_remove_buffers(&mut self, nbufs: u32, bgid: u16) -> io::Result<()> {
+		self.uring.submission_queue().bulk().submit_with(|entry| {
+			entry.remove_buffers(nbufs, bgid);
+			entry.user_data = 0;
+			Ok(())
+		}).map_err(sq_full_map_err)
+	}
+
+	// addr needs to live until the entry was submitted
+	fn queue_async_connect(&mut self, fd: io_uring::FileDescriptor, addr: *const libc::sockaddr, addrlen: libc::socklen_t, timeout: Option<*const io_uring::KernelTimespec>, reg: RawRegistration) -> io::Result<()> {
+		submit_with_link_timeout(self.uring.submission_queue(), &mut self.completion_state, timeout, reg, |entry| {
+			unsafe {
+				entry.connect(fd, addr, addrlen);
+			}
+		})
+	}
+
+	fn queue_async_splice(&mut self, fd_in: RawFd, off_in: Option<u64>, fd_out: RawFd, off_out: Option<u64>, len: u32, reg: RawRegistration) -> io::Result<()> {
+		let user_data = reg.user_data();
+		self.uring.submission_queue().bulk().submit_with(|entry| {
+			entry.splice(
+				io_uring::FileDescriptor::FD(fd_in),
+				off_in,
+				io_uring::FileDescriptor::FD(fd_out),
+				off_out,
+				len,
+				io_uring::SpliceFlags::default(),
+			);
+			entry.user_data = unsafe { reg.into_user_data() };
+			Ok(())
+		}).map_err(sq_full_map_err)?;
+		self.completion_state.started(user_data);
+		Ok(())
+	}
+
+	fn queue_async_tee(&mut self, fd_in: RawFd, fd_out: RawFd, len: u32, reg: RawRegistration) -> io::Result<()> {
+		let user_data = reg.user_data();
+		self.uring.submission_queue().bulk().submit_with(|entry| {
+			entry.tee(
+				io_uring::FileDescriptor::FD(fd_in),
+				io_uring::FileDescriptor::FD(fd_out),
+				len,
+				io_uring::SpliceFlags::default(),
+			);
+			entry.user_data = unsafe { reg.into_user_data() };
+			Ok(())
+		}).map_err(sq_full_map_err)?;
+		self.completion_state.started(user_data);
+		Ok(())
+	}
+
+	fn queue_timeout(&mut self, timespec: *const io_uring::KernelTimespec, flags: io_uring::TimeoutFlags, reg: RawRegistration) -> io::Result<()> {
+		let user_data = reg.user_data();
+		self.uring.submission_queue().bulk().submit_with(|entry| {
+			unsafe {
+				entry.timeout(timespec, 0, flags);
+				entry.user_data = reg.into_user_data();
+			}
+			Ok(())
+		}).map_err(sq_full_map_err)?;
+		self.completion_state.started(user_data);
+		Ok(())
+	}
+
+	// fire-and-forget: completion of the canceled operation is handled
+	// through its own registration.
+	fn queue_cancel(&mut self, user_data: u64) -> io::Result<()> {
+		self.uring.submission_queue().bulk().submit_with(|entry| {
+			entry.async_cancel(user_data, io_uring::CancelFlags::default());
+			entry.user_data = 0;
+			Ok(())
+		}).map_err(sq_full_map_err)
+	}
+
+	// completes once per received chunk while flagged `F_MORE`
+	fn queue_async_recv_multishot(&mut self, fd: RawFd, bgid: u16, reg: RawRegistration) -> io::Result<()> {
+		let user_data = reg.user_data();
+		self.uring.submission_queue().bulk().submit_with(|entry| {
+			entry.recv_multishot(
+				io_uring::FileDescriptor::FD(fd),
+				bgid,
+				io_uring::MsgFlags::default(),
+			);
+			entry.user_data = unsafe { reg.into_user_data() };
+			Ok(())
+		}).map_err(sq_full_map_err)?;
+		self.completion_state.started(user_data);
+		Ok(())
+	}
+
+	// completes once per accepted connection while flagged `F_MORE`
+	fn queue_async_accept_multishot(&mut self, fd: RawFd, reg: RawRegistration) -> io::Result<()> {
+		let user_data = reg.user_data();
+		self.uring.submission_queue().bulk().submit_with(|entry| {
+			entry.accept_multishot(
+				io_uring::FileDescriptor::FD(fd),
+				io_uring::AcceptFlags::CLOEXEC,
+			);
+			entry.user_data = unsafe { reg.into_user_data() };
+			Ok(())
+		}).map_err(sq_full_map_err)?;
+		self.completion_state.started(user_data);
+		Ok(())
+	}
+
+	// with `multishot` the poll completes once per event while flagged
+	// `F_MORE`
+	fn queue_async_poll(&mut self, fd: RawFd, flags: io_uring::PollFlags, multishot: bool, reg: RawRegistration) -> io::Result<()> {
+		let user_data = reg.user_data();
+		let add_flags = if multishot { io_uring::PollAddFlags::ADD_MULTI } else { io_uring::PollAddFlags::default() };
 		self.uring.submission_queue().bulk().submit_with(|entry| {
 			unsafe {
 				entry.poll_add(
 					io_uring::FileDescriptor::FD(fd),
 					flags,
+					add_flags,
 				);
 				entry.user_data = reg.into_user_data();
 			}
 			Ok(())
 		}).map_err(sq_full_map_err)?;
-		self.completion_state.active_wait += 1;
+		self.completion_state.started(user_data);
 		Ok(())
 	}
+
+	// fire-and-forget: if the poll isn't active anymore the new events
+	// are used when it is queued again
+	fn queue_poll_update(&mut self, user_data: u64, flags: io_uring::PollFlags, multishot: bool) -> io::Result<()> {
+		let add_flags = if multishot { io_uring::PollAddFlags::ADD_MULTI } else { io_uring::PollAddFlags::default() };
+		self.uring.submission_queue().bulk().submit_with(|entry| {
+			entry.poll_update(user_data, None, Some(flags), add_flags);
+			entry.user_data = 0;
+			Ok(())
+		}).map_err(sq_full_map_err)
+	}
 }
 
 struct InnerMut {
@@ -347,6 +868,61 @@ impl InnerMut {
 	}
 }
 
+// registration of a submitted operation; cancels the operation if
+// dropped before it completed.
+//
+// the kernel completion still holds a reference to the registration, so
+// its data (buffers, ...) stays alive until the operation actually
+// finished (completed or canceled).
+struct ActiveRegistration<T: 'static> {
+	handle: Handle,
+	registration: Registration<T>,
+}
+
+impl<T: 'static> ActiveRegistration<T> {
+	fn new(handle: &Handle, registration: Registration<T>) -> Self {
+		ActiveRegistration {
+			handle: handle.clone(),
+			registration,
+		}
+	}
+}
+
+impl<T: 'static> std::ops::Deref for ActiveRegistration<T> {
+	type Target = Registration<T>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.registration
+	}
+}
+
+impl<T: 'static> std::ops::DerefMut for ActiveRegistration<T> {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.registration
+	}
+}
+
+impl<T: 'static> Drop for ActiveRegistration<T> {
+	fn drop(&mut self) {
+		if self.registration.is_finished() {
+			self.registration.handle_abandoned();
+			return;
+		}
+		let user_data = self.registration.user_data();
+		if let Ok(mut im) = self.handle.inner_mut() {
+			if let Err(e) = im.pinned().queue_cancel(user_data) {
+				log::warn!("failed to cancel dropped operation: {}", e);
+			}
+		}
+	}
+}
+
+impl<T: 'static> fmt::Debug for ActiveRegistration<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.registration.fmt(f)
+	}
+}
+
 pub struct Reactor {
 	inner: Rc<UnsafeCell<Inner>>,
 }
@@ -375,6 +951,32 @@ impl Reactor {
 	pub fn handle(&self) -> Handle {
 		Handle(Rc::downgrade(&self.inner))
 	}
+
+	/// Cancel all active operations and wait up to `timeout` for their
+	/// completions.
+	///
+	/// Kernels before 5.19 can't cancel everything at once; operations
+	/// are canceled one by one there.
+	///
+	/// Afterwards the reactor doesn't accept new operations; fails with
+	/// `io::ErrorKind::TimedOut` if some operations didn't complete in
+	/// time (it can be called again to wait longer).
+	pub fn shutdown(&mut self, timeout: Duration) -> io::Result<()> {
+		self.inner_mut().pinned().shutdown(timeout)
+	}
+}
+
+impl Drop for Reactor {
+	fn drop(&mut self) {
+		const DROP_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+		if let Err(e) = self.inner_mut().pinned().shutdown(DROP_SHUTDOWN_TIMEOUT) {
+			// kernel might still write to memory owned by the reactor
+			// or by registrations; never free it.
+			log::error!("{}; leaking reactor", e);
+			std::mem::forget(self.inner.clone());
+		}
+	}
 }
 
 impl tokio_executor::park::Park for Reactor {
@@ -410,28 +1012,218 @@ impl Handle {
 		let inner = self.0.upgrade().ok_or_else(|| {
 			io::Error::new(io::ErrorKind::Other, "uring reactor dead")
 		})?;
+		if unsafe { &*inner.get() }.is_shutdown {
+			return Err(io::Error::new(io::ErrorKind::Other, "uring reactor shut down"));
+		}
 
 		Ok(InnerMut { inner })
 	}
 
-	pub fn async_read<T, F>(&self, file: F, offset: u64, buf: T) -> AsyncRead<T, F>
+	/// number of completions the kernel dropped because the completion
+	/// queue overflowed
+	///
+	/// The operations they belonged to never finish; parking the reactor
+	/// fails when new losses are detected.
+	pub fn lost_completions(&self) -> u64 {
+		match self.0.upgrade() {
+			Some(inner) => unsafe { &*inner.get() }.lost_completions,
+			None => 0,
+		}
+	}
+
+	/// whether the kernel supports the given opcode
+	///
+	/// returns false if the reactor is gone or the kernel is too old to
+	/// be probed.
+	pub fn supports(&self, op: io_uring::Operation) -> bool {
+		match self.0.upgrade() {
+			Some(inner) => unsafe { &*inner.get() }.supports(op),
+			None => false,
+		}
+	}
+
+	/// Register `file` in the reactor's fixed file table
+	///
+	/// Operations on the returned slot skip the per-operation fd lookup
+	/// (see `async_read_fixed`); the kernel keeps the file open until
+	/// the slot is dropped.
+	pub fn register_file<F: AsRawFd>(&self, file: &F) -> io::Result<io_uring::FixedFile> {
+		let mut im = self.inner_mut()?;
+		let inner = im.pinned().get_mut();
+		if inner.fixed_files.is_none() {
+			inner.fixed_files = Some(io_uring::FixedFiles::register(inner.uring.file(), FIXED_FILE_SLOTS)?);
+		}
+		inner.fixed_files.as_ref().expect("fixed file table").insert(file)
+	}
+
+	/// Register `count` buffers of `buf_len` bytes with the reactor
+	///
+	/// Buffers from the returned pool can be used with
+	/// `async_read_fixed` and `async_write_fixed`; only one pool can be
+	/// registered at a time, and it stays registered until it is passed
+	/// to `unregister_buffers`.
+	pub fn register_buffers(&self, count: u16, buf_len: usize) -> io::Result<io_uring::FixedBuffers> {
+		let mut im = self.inner_mut()?;
+		let inner = im.pinned().get_mut();
+		if inner.fixed_buffers {
+			return Err(io::Error::new(io::ErrorKind::Other, "buffers already registered"));
+		}
+		let buffers = io_uring::FixedBuffers::register(inner.uring.file(), count, buf_len)?;
+		inner.fixed_buffers = true;
+		Ok(buffers)
+	}
+
+	/// Unregister the pool from `register_buffers`, so a new one can be
+	/// registered
+	///
+	/// Handed out `FixedBuf`s stay usable as memory, but must not be
+	/// used with fixed operations anymore.
+	pub fn unregister_buffers(&self, buffers: io_uring::FixedBuffers) -> io::Result<()> {
+		let mut im = self.inner_mut()?;
+		let inner = im.pinned().get_mut();
+		if !inner.fixed_buffers {
+			return Err(io::Error::new(io::ErrorKind::Other, "no buffers registered"));
+		}
+		buffers.unregister(inner.uring.file())?;
+		inner.fixed_buffers = false;
+		Ok(())
+	}
+
+	/// Read into a buffer from `register_buffers`; `file` can be a slot
+	/// from `register_file`.
+	///
+	/// Like `async_read`, the future returns buffer and file.
+	pub fn async_read_fixed<F>(&self, file: F, offset: u64, buf: io_uring::FixedBuf, timeout: Option<Duration>) -> AsyncRead<io_uring::FixedBuf, F>
+	where
+		F: AsFileDescriptor + 'static,
+	{
+		AsyncRead::new_fixed(self, file, offset, buf, timeout)
+	}
+
+	/// Write the first `len` bytes of a buffer from `register_buffers`;
+	/// `file` can be a slot from `register_file`.
+	///
+	/// Like `async_write`, the future returns buffer and file.
+	pub fn async_write_fixed<F>(&self, file: F, offset: u64, buf: io_uring::FixedBuf, len: usize, timeout: Option<Duration>) -> AsyncWrite<io_uring::FixedBuf, F>
+	where
+		F: AsFileDescriptor + 'static,
+	{
+		AsyncWrite::new_fixed(self, file, offset, buf, len, timeout)
+	}
+
+	/// Read into a buffer the kernel picks from `pool` once data is
+	/// available; `file` can be a slot from `register_file`.
+	///
+	/// The future returns the buffer (`None` at end of file) and file;
+	/// it fails with `ENOBUFS` if all buffers of the pool are in use.
+	pub fn async_read_pooled<F>(&self, file: F, offset: u64, pool: &BufferPool) -> AsyncReadPooled<F>
+	where
+		F: AsFileDescriptor + 'static,
+	{
+		AsyncReadPooled::new(self, file, offset, pool)
+	}
+
+	/// With a `timeout` the kernel cancels the read if it doesn't
+	/// complete in time, and it fails with `io::ErrorKind::TimedOut`.
+	pub fn async_read<T, F>(&self, file: F, offset: u64, buf: T, timeout: Option<Duration>) -> AsyncRead<T, F>
 	where
 		T: AsMut<[u8]> + 'static,
 		F: AsRawFd + 'static,
 	{
-		AsyncRead::new(self, file, offset, buf)
+		AsyncRead::new(self, file, offset, buf, timeout)
 	}
 
-	pub fn async_write<T: AsRef<[u8]> + 'static, F: AsRawFd + 'static>(&self, file: F, offset: u64, buf: T) -> AsyncWrite<T, F>
+	/// With a `timeout` the kernel cancels the write if it doesn't
+	/// complete in time, and it fails with `io::ErrorKind::TimedOut`.
+	pub fn async_write<T: AsRef<[u8]> + 'static, F: AsRawFd + 'static>(&self, file: F, offset: u64, buf: T, timeout: Option<Duration>) -> AsyncWrite<T, F>
 	where
 		T: AsRef<[u8]> + 'static,
 		F: AsRawFd + 'static,
 	{
-		AsyncWrite::new(self, file, offset, buf)
+		AsyncWrite::new(self, file, offset, buf, timeout)
+	}
+
+	/// Connect socket `file` (should be non-blocking) to `addr`
+	///
+	/// With a `timeout` the kernel cancels the connect if it doesn't
+	/// complete in time, and it fails with `io::ErrorKind::TimedOut`.
+	pub fn async_connect<F>(&self, file: F, addr: &SocketAddr, timeout: Option<Duration>) -> AsyncConnect<F>
+	where
+		F: AsRawFd + 'static,
+	{
+		AsyncConnect::new(self, file, addr, timeout)
+	}
+
+	/// Move up to `len` bytes from `file_in` to `file_out` without
+	/// copying through user space; one of them must be a pipe.
+	///
+	/// Offsets must be `None` for pipes.
+	pub fn async_splice<I, O>(&self, file_in: I, off_in: Option<u64>, file_out: O, off_out: Option<u64>, len: u32) -> AsyncSplice<I, O>
+	where
+		I: AsRawFd + 'static,
+		O: AsRawFd + 'static,
+	{
+		AsyncSplice::new(self, file_in, off_in, file_out, off_out, len)
+	}
+
+	/// Duplicate up to `len` bytes from pipe `file_in` to pipe
+	/// `file_out` without consuming them.
+	pub fn async_tee<I, O>(&self, file_in: I, file_out: O, len: u32) -> AsyncSplice<I, O>
+	where
+		I: AsRawFd + 'static,
+		O: AsRawFd + 'static,
+	{
+		AsyncSplice::new_tee(self, file_in, file_out, len)
+	}
+
+	/// Stream of connections accepted on listening socket `fd` by a
+	/// single multishot accept (kernel 5.19+)
+	///
+	/// The kernel operation is re-armed when it ends; older kernels
+	/// fail with `EINVAL`.
+	pub fn async_accept_multishot(&self, fd: RawFd) -> AsyncAccept {
+		AsyncAccept::new(self, fd)
+	}
+
+	/// Stream of chunks received on socket `file` by a single multishot
+	/// recv (kernel 6.0+), in buffers picked from `pool`
+	///
+	/// The kernel operation is re-armed when it ends; the stream ends at
+	/// end of stream. If the pool runs out of buffers the stream yields
+	/// `ENOBUFS`, and polling again re-arms it.
+	pub fn async_recv_multishot<F>(&self, file: F, pool: &BufferPool) -> AsyncRecv<F>
+	where
+		F: AsRawFd + 'static,
+	{
+		AsyncRecv::new(self, file, pool)
 	}
 
 	pub fn async_poll(&self, fd: RawFd, flags: io_uring::PollFlags) -> AsyncPoll {
-		AsyncPoll::new(self, fd, flags)
+		AsyncPoll::new(self, fd, flags, false)
+	}
+
+	/// Like `async_poll`, but a single kernel poll stays active across
+	/// events (kernel 5.13+); it is re-armed if the kernel ends it.
+	pub fn async_poll_multishot(&self, fd: RawFd, flags: io_uring::PollFlags) -> AsyncPoll {
+		AsyncPoll::new(self, fd, flags, true)
+	}
+
+	/// Future completing after `duration`
+	pub fn sleep(&self, duration: Duration) -> Sleep {
+		Sleep::new(self, duration)
+	}
+
+	/// Stream yielding every `period` (first after one `period`)
+	pub fn interval(&self, period: Duration) -> Interval {
+		Interval::new(self, period)
+	}
+
+	/// Fail `future` with `TimeoutError::Elapsed` if it doesn't
+	/// complete within `timeout`
+	///
+	/// For streams the timeout applies to each item.
+	pub fn timeout<F>(&self, future: F, timeout: Duration) -> Timeout<F> {
+		Timeout::new(self, future, timeout)
 	}
 }
 
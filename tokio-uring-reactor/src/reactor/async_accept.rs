@@ -0,0 +1,120 @@
+use std::{
+	io,
+	os::unix::io::RawFd,
+};
+
+use crate::{
+	reactor::{
+		Handle,
+	},
+	registration::{
+		Registration,
+		UringResult,
+	},
+};
+
+/// Stream of accepted fds from a multishot accept
+///
+/// Each item is a new connection; the kernel operation is re-armed on
+/// the next poll after it ended (the item before that is an error if it
+/// ended with one).
+#[derive(Debug)]
+pub struct AsyncAccept {
+	handle: Handle,
+	fd: RawFd,
+	active: bool,
+	registration: Registration<()>,
+}
+
+impl AsyncAccept {
+	pub fn new(handle: &Handle, fd: RawFd) -> AsyncAccept {
+		let mut registration = Registration::new(());
+		// close connections accepted after the stream was dropped
+		registration.on_abandoned(|result| {
+			if let Ok(fd) = result.io_result() {
+				unsafe { libc::close(fd as RawFd); }
+			}
+		});
+
+		AsyncAccept {
+			active: false,
+			handle: handle.clone(),
+			fd,
+			registration,
+		}
+	}
+
+	fn arm(&mut self) -> io::Result<()> {
+		let mut im = self.handle.inner_mut()?;
+		im.pinned().queue_async_accept_multishot(self.fd, self.registration.to_raw())?;
+		self.active = true;
+		Ok(())
+	}
+
+	fn accepted(&mut self, r: UringResult) -> io::Result<RawFd> {
+		if !r.has_more() {
+			// kernel ended the operation
+			self.active = false;
+		}
+		Ok(r.io_result()? as RawFd)
+	}
+}
+
+impl Drop for AsyncAccept {
+	fn drop(&mut self) {
+		self.registration.handle_abandoned();
+		if !self.active || self.registration.is_finished() {
+			return;
+		}
+		let user_data = self.registration.user_data();
+		if let Ok(mut im) = self.handle.inner_mut() {
+			if let Err(e) = im.pinned().queue_cancel(user_data) {
+				log::warn!("failed to cancel dropped accept: {}", e);
+			}
+		}
+	}
+}
+
+impl futures::Stream for AsyncAccept {
+	type Item = RawFd;
+	type Error = io::Error;
+
+	fn poll(&mut self) -> futures::Poll<Option<Self::Item>, Self::Error> {
+		if !self.active {
+			self.arm()?;
+			self.registration.track();
+			return Ok(futures::Async::NotReady);
+		}
+		match self.registration.poll_stream_and_reset() {
+			futures::Async::NotReady => Ok(futures::Async::NotReady),
+			futures::Async::Ready(r) => Ok(futures::Async::Ready(Some(self.accepted(r)?))),
+		}
+	}
+}
+
+#[cfg(feature = "nightly-async")]
+use std::{
+	pin::Pin,
+	task::Poll,
+	task::Context,
+};
+
+#[cfg(feature = "nightly-async")]
+impl futures_core::Stream for AsyncAccept {
+	type Item = io::Result<RawFd>;
+
+	fn poll_next(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this: &mut Self = &mut *self;
+		if !this.active {
+			if let Err(e) = this.arm() {
+				return Poll::Ready(Some(Err(e)));
+			}
+			this.registration.track_async(ctx.waker());
+			return Poll::Pending;
+		}
+		match this.registration.poll_stream_and_reset_async(ctx.waker()) {
+			Poll::Pending => Poll::Pending,
+			Poll::Ready(r) => Poll::Ready(Some(this.accepted(r))),
+		}
+	}
+}
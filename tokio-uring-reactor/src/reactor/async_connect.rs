@@ -0,0 +1,233 @@
+use std::{
+	fmt,
+	io,
+	net::SocketAddr,
+	os::unix::io::{AsRawFd},
+	time::Duration,
+};
+
+use crate::{
+	reactor::{
+		ActiveRegistration,
+		Handle,
+	},
+	registration::{
+		Registration,
+	},
+};
+
+// #[non_exhaustive] TODO ?
+pub struct AsyncConnectError<F> {
+	pub error: io::Error,
+	pub file: F,
+}
+
+impl<F> From<AsyncConnectError<F>> for io::Error {
+	fn from(e: AsyncConnectError<F>) -> io::Error {
+		e.error
+	}
+}
+
+// storage for `sockaddr_in` or `sockaddr_in6`
+fn sockaddr_from(addr: &SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+	let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+	let len = match addr {
+		SocketAddr::V4(addr) => {
+			let sin = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
+			sin.sin_family = libc::AF_INET as libc::sa_family_t;
+			sin.sin_port = addr.port().to_be();
+			sin.sin_addr.s_addr = u32::from_ne_bytes(addr.ip().octets());
+			std::mem::size_of::<libc::sockaddr_in>()
+		},
+		SocketAddr::V6(addr) => {
+			let sin6 = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6) };
+			sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+			sin6.sin6_port = addr.port().to_be();
+			sin6.sin6_flowinfo = addr.flowinfo();
+			sin6.sin6_addr.s6_addr = addr.ip().octets();
+			sin6.sin6_scope_id = addr.scope_id();
+			std::mem::size_of::<libc::sockaddr_in6>()
+		},
+	};
+	(storage, len as libc::socklen_t)
+}
+
+struct Context<F: 'static> {
+	// addr and timeout need to live until submission; the registration
+	// keeps them in place.
+	addr: libc::sockaddr_storage,
+	addrlen: libc::socklen_t,
+	timeout: Option<io_uring::KernelTimespec>,
+	file: F,
+}
+
+impl<F: 'static> Context<F> {
+	fn with_error(self, error: io::Error) -> AsyncConnectError<F> {
+		AsyncConnectError {
+			error,
+			file: self.file,
+		}
+	}
+
+	fn with_result(self, result: io::Result<u32>) -> Result<F, AsyncConnectError<F>> {
+		match result {
+			// the registration reports an expired linked timeout as ETIMEDOUT
+			Err(e) => Err(self.with_error(e)),
+			Ok(_) => Ok(self.file),
+		}
+	}
+}
+
+enum State<F: 'static> {
+	Pending(ActiveRegistration<Context<F>>),
+	InitFailed(AsyncConnectError<F>),
+	Closed,
+}
+
+impl<F: 'static> fmt::Debug for State<F> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			State::Pending(ref p) => f.debug_tuple("Pending").field(p).finish(),
+			State::InitFailed(ref e) => f.debug_tuple("InitFailed").field(&e.error).finish(),
+			State::Closed => f.debug_tuple("Closed").finish(),
+		}
+	}
+}
+
+/// Future connecting a socket; resolves to the socket once connected
+pub struct AsyncConnect<F: 'static>(State<F>);
+
+impl<F> AsyncConnect<F> {
+	pub(super) fn new(handle: &Handle, file: F, addr: &SocketAddr, timeout: Option<Duration>) -> AsyncConnect<F>
+	where
+		F: AsRawFd + 'static,
+	{
+		let fd = io_uring::FileDescriptor::FD(file.as_raw_fd());
+		let (addr, addrlen) = sockaddr_from(addr);
+		let context = Context {
+			addr,
+			addrlen,
+			timeout: timeout.map(io_uring::KernelTimespec::from),
+			file,
+		};
+
+		let mut im = match handle.inner_mut() {
+			Err(e) => return AsyncConnect(State::InitFailed(context.with_error(e))),
+			Ok(im) => im,
+		};
+
+		let mut reg = Registration::new(context);
+		let queue_result = {
+			let d = unsafe { &*reg.data_mut() };
+			let addr = &d.addr as *const _ as *const libc::sockaddr;
+			let timeout = d.timeout.as_ref().map(|t| t as *const _);
+			im.pinned().queue_async_connect(fd, addr, d.addrlen, timeout, reg.to_raw())
+		};
+		if let Err(e) = queue_result {
+			let context = reg.abort().expect("registration context");
+			return AsyncConnect(State::InitFailed(context.with_error(e)));
+		}
+		AsyncConnect(State::Pending(ActiveRegistration::new(handle, reg)))
+	}
+}
+
+impl<F: 'static> fmt::Debug for AsyncConnect<F> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("AsyncConnect").field(&self.0).finish()
+	}
+}
+
+impl<F: 'static> futures::Future for AsyncConnect<F> {
+	type Item = F;
+	type Error = AsyncConnectError<F>;
+
+	fn poll(&mut self) -> futures::Poll<Self::Item, Self::Error> {
+		match self.0 {
+			State::Pending(ref mut p) => {
+				match p.poll() {
+					futures::Async::NotReady => Ok(futures::Async::NotReady),
+					futures::Async::Ready((r, context)) => {
+						let result = context.with_result(r.io_result()).map(futures::Async::Ready);
+						self.0 = State::Closed;
+						result
+					}
+				}
+			},
+			_ => {
+				match std::mem::replace(&mut self.0, State::Closed) {
+					State::Pending(_) => unreachable!(),
+					State::InitFailed(e) => Err(e),
+					State::Closed => panic!("already finished"),
+				}
+			}
+		}
+	}
+}
+
+#[cfg(feature = "nightly-async")]
+use std::{
+	pin::Pin,
+	task,
+	future::Future,
+	task::Poll,
+};
+
+#[cfg(feature = "nightly-async")]
+impl<F: Unpin + 'static> Future for AsyncConnect<F> {
+	type Output = Result<F, AsyncConnectError<F>>;
+
+	fn poll(mut self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+		let this: &mut Self = &mut *self;
+		match this.0 {
+			State::Pending(ref mut p) => {
+				match p.poll_async(ctx.waker()) {
+					Poll::Pending => Poll::Pending,
+					Poll::Ready((r, context)) => {
+						let result = context.with_result(r.io_result());
+						this.0 = State::Closed;
+						Poll::Ready(result)
+					}
+				}
+			},
+			_ => {
+				match std::mem::replace(&mut this.0, State::Closed) {
+					State::Pending(_) => unreachable!(),
+					State::InitFailed(e) => Poll::Ready(Err(e)),
+					State::Closed => panic!("already finished"),
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6};
+
+	use super::*;
+
+	#[test]
+	fn sockaddr_v4() {
+		let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 0x1234));
+		let (storage, len) = sockaddr_from(&addr);
+		assert_eq!(len as usize, std::mem::size_of::<libc::sockaddr_in>());
+		let sin = unsafe { &*(&storage as *const _ as *const libc::sockaddr_in) };
+		assert_eq!(sin.sin_family, libc::AF_INET as libc::sa_family_t);
+		assert_eq!(sin.sin_port.to_ne_bytes(), [0x12, 0x34]);
+		assert_eq!(sin.sin_addr.s_addr.to_ne_bytes(), [192, 168, 1, 2]);
+	}
+
+	#[test]
+	fn sockaddr_v6() {
+		let ip = Ipv6Addr::new(0xfe80, 0, 0, 0, 1, 2, 3, 4);
+		let addr = SocketAddr::V6(SocketAddrV6::new(ip, 0x1234, 5, 6));
+		let (storage, len) = sockaddr_from(&addr);
+		assert_eq!(len as usize, std::mem::size_of::<libc::sockaddr_in6>());
+		let sin6 = unsafe { &*(&storage as *const _ as *const libc::sockaddr_in6) };
+		assert_eq!(sin6.sin6_family, libc::AF_INET6 as libc::sa_family_t);
+		assert_eq!(sin6.sin6_port.to_ne_bytes(), [0x12, 0x34]);
+		assert_eq!(sin6.sin6_flowinfo, 5);
+		assert_eq!(sin6.sin6_scope_id, 6);
+		assert_eq!(sin6.sin6_addr.s6_addr, ip.octets());
+	}
+}
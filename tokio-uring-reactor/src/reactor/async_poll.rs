@@ -12,28 +12,64 @@ use crate::{
 	},
 };
 
-// TODO: Dropping AsyncPoll should trigger a POLL_DEL
 #[derive(Debug)]
 pub struct AsyncPoll {
 	handle: Handle,
 	fd: RawFd,
 	active: bool,
+	// keep a single kernel poll active across events
+	multishot: bool,
 	flags: io_uring::PollFlags,
 	registration: Registration<()>,
 }
 
 impl AsyncPoll {
-	pub fn new(handle: &Handle, fd: RawFd, flags: io_uring::PollFlags) -> AsyncPoll {
+	pub fn new(handle: &Handle, fd: RawFd, flags: io_uring::PollFlags, multishot: bool) -> AsyncPoll {
 		let registration = Registration::new(());
 
 		AsyncPoll {
 			active: false,
 			handle: handle.clone(),
 			fd,
+			multishot,
 			flags,
 			registration,
 		}
 	}
+
+	/// events currently polled for
+	pub fn events(&self) -> io_uring::PollFlags {
+		self.flags
+	}
+
+	/// change the events to poll for
+	///
+	/// An active kernel poll is updated in place (`IORING_POLL_UPDATE`,
+	/// kernel 5.13+); otherwise the new events are used when the poll is
+	/// queued next time.
+	pub fn update_events(&mut self, flags: io_uring::PollFlags) -> io::Result<()> {
+		self.flags = flags;
+		if !self.active || self.registration.is_finished() {
+			return Ok(());
+		}
+		let user_data = self.registration.user_data();
+		let mut im = self.handle.inner_mut()?;
+		im.pinned().queue_poll_update(user_data, flags, self.multishot)
+	}
+}
+
+impl Drop for AsyncPoll {
+	fn drop(&mut self) {
+		if !self.active || self.registration.is_finished() {
+			return;
+		}
+		let user_data = self.registration.user_data();
+		if let Ok(mut im) = self.handle.inner_mut() {
+			if let Err(e) = im.pinned().queue_cancel(user_data) {
+				log::warn!("failed to cancel dropped poll: {}", e);
+			}
+		}
+	}
 }
 
 impl futures::Stream for AsyncPoll {
@@ -44,7 +80,7 @@ impl futures::Stream for AsyncPoll {
 		if !self.active {
 			// println!("Register fd {} for events {:?}", self.fd, self.flags);
 			let mut im = self.handle.inner_mut()?;
-			im.pinned().queue_async_poll(self.fd, self.flags, self.registration.to_raw())?;
+			im.pinned().queue_async_poll(self.fd, self.flags, self.multishot, self.registration.to_raw())?;
 			self.active = true;
 			self.registration.track();
 			return Ok(futures::Async::NotReady);
@@ -52,11 +88,11 @@ impl futures::Stream for AsyncPoll {
 		match self.registration.poll_stream_and_reset() {
 			futures::Async::NotReady => Ok(futures::Async::NotReady),
 			futures::Async::Ready(r) => {
-				self.active = false;
-				if r.result < 0 {
-					return Err(io::Error::from_raw_os_error(-r.result));
+				if !r.has_more() {
+					// kernel poll finished; queue a new one next time
+					self.active = false;
 				}
-				let flags = io_uring::PollFlags::from_bits_truncate(r.result as u16);
+				let flags = io_uring::PollFlags::from_bits_truncate(r.io_result()? as u16);
 				Ok(futures::Async::Ready(Some(flags)))
 			}
 		}
@@ -79,7 +115,7 @@ impl Future for AsyncPoll {
 		if !self.active {
 			// println!("Register fd {} for events {:?}", self.fd, self.flags);
 			let mut im = self.handle.inner_mut()?;
-			im.pinned().queue_async_poll(self.fd, self.flags, self.registration.to_raw())?;
+			im.pinned().queue_async_poll(self.fd, self.flags, self.multishot, self.registration.to_raw())?;
 			self.active = true;
 			self.registration.track_async(ctx.waker());
 			return Poll::Pending;
@@ -87,11 +123,11 @@ impl Future for AsyncPoll {
 		match self.registration.poll_stream_and_reset_async(ctx.waker()) {
 			Poll::Pending => Poll::Pending,
 			Poll::Ready(r) => {
-				self.active = false;
-				if r.result < 0 {
-					return Poll::Ready(Err(io::Error::from_raw_os_error(-r.result)));
+				if !r.has_more() {
+					// kernel poll finished; queue a new one next time
+					self.active = false;
 				}
-				let flags = io_uring::PollFlags::from_bits_truncate(r.result as u16);
+				let flags = io_uring::PollFlags::from_bits_truncate(r.io_result()? as u16);
 				Poll::Ready(Ok(flags))
 			}
 		}
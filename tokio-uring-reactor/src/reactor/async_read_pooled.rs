@@ -0,0 +1,187 @@
+use std::{
+	fmt,
+	io,
+};
+
+use crate::{
+	io::AsFileDescriptor,
+	reactor::{
+		ActiveRegistration,
+		BufferPool,
+		Handle,
+		PooledBuf,
+	},
+	registration::{
+		Registration,
+		UringResult,
+	},
+};
+
+// #[non_exhaustive] TODO ?
+pub struct AsyncReadPooledError<F> {
+	pub error: io::Error,
+	pub file: F,
+}
+
+impl<F> From<AsyncReadPooledError<F>> for io::Error {
+	fn from(e: AsyncReadPooledError<F>) -> io::Error {
+		e.error
+	}
+}
+
+struct Context<F: 'static> {
+	// only the length is used; the kernel picks the buffer
+	iovec: [libc::iovec; 1],
+	pool: BufferPool,
+	file: F,
+}
+
+impl<F: 'static> Context<F> {
+	fn with_error(self, error: io::Error) -> AsyncReadPooledError<F> {
+		AsyncReadPooledError {
+			error,
+			file: self.file,
+		}
+	}
+
+	fn with_result(self, result: UringResult) -> Result<(Option<PooledBuf>, F), AsyncReadPooledError<F>> {
+		// take the buffer even if the read failed, so it gets recycled
+		let buf = result.flags.buffer_id().map(|bid| self.pool.take(bid, 0));
+		match (result.io_result(), buf) {
+			(Err(e), _) => Err(self.with_error(e)),
+			(Ok(0), _) => Ok((None, self.file)),
+			(Ok(n), Some(mut buf)) => {
+				buf.set_len(n as usize);
+				Ok((Some(buf), self.file))
+			},
+			(Ok(_), None) => Err(self.with_error(io::Error::new(io::ErrorKind::Other, "read completed without buffer"))),
+		}
+	}
+}
+
+enum State<F: 'static> {
+	Pending(ActiveRegistration<Context<F>>),
+	InitFailed(AsyncReadPooledError<F>),
+	Closed,
+}
+
+impl<F: 'static> fmt::Debug for State<F> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			State::Pending(ref p) => f.debug_tuple("Pending").field(p).finish(),
+			State::InitFailed(ref e) => f.debug_tuple("InitFailed").field(&e.error).finish(),
+			State::Closed => f.debug_tuple("Closed").finish(),
+		}
+	}
+}
+
+/// Read into a buffer the kernel picks from a `BufferPool` once data is
+/// available
+///
+/// Returns `None` instead of a buffer at end of file.
+pub struct AsyncReadPooled<F: 'static>(State<F>);
+
+impl<F: AsFileDescriptor + 'static> AsyncReadPooled<F> {
+	pub(super) fn new(handle: &Handle, file: F, offset: u64, pool: &BufferPool) -> AsyncReadPooled<F> {
+		let context = Context {
+			iovec: [ libc::iovec {
+				iov_base: std::ptr::null_mut(),
+				iov_len: pool.buf_len(),
+			} ],
+			pool: pool.clone(),
+			file,
+		};
+
+		let mut im = match handle.inner_mut() {
+			Err(e) => return AsyncReadPooled(State::InitFailed(context.with_error(e))),
+			Ok(im) => im,
+		};
+
+		let fd = context.file.as_file_descriptor();
+		let bgid = pool.bgid();
+		pool.provide_pending(&mut im.pinned());
+		let mut reg = Registration::new(context);
+		let abandoned_pool = pool.clone();
+		reg.on_abandoned(move |result| {
+			if let Some(bid) = result.flags.buffer_id() {
+				abandoned_pool.release(bid);
+			}
+		});
+		let queue_result = {
+			let iovec = unsafe { &reg.data_mut().iovec };
+			im.pinned().queue_async_read_select(fd, offset, iovec, bgid, reg.to_raw())
+		};
+		if let Err(e) = queue_result {
+			let context = reg.abort().expect("registration context");
+			return AsyncReadPooled(State::InitFailed(context.with_error(e)));
+		}
+		AsyncReadPooled(State::Pending(ActiveRegistration::new(handle, reg)))
+	}
+}
+
+impl<F: 'static> fmt::Debug for AsyncReadPooled<F> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("AsyncReadPooled").field(&self.0).finish()
+	}
+}
+
+impl<F: 'static> futures::Future for AsyncReadPooled<F> {
+	type Item = (Option<PooledBuf>, F);
+	type Error = AsyncReadPooledError<F>;
+
+	fn poll(&mut self) -> futures::Poll<Self::Item, Self::Error> {
+		match self.0 {
+			State::Pending(ref mut p) => {
+				match p.poll() {
+					futures::Async::NotReady => Ok(futures::Async::NotReady),
+					futures::Async::Ready((r, context)) => {
+						self.0 = State::Closed;
+						context.with_result(r).map(futures::Async::Ready)
+					}
+				}
+			},
+			_ => {
+				match std::mem::replace(&mut self.0, State::Closed) {
+					State::Pending(_) => unreachable!(),
+					State::InitFailed(e) => Err(e),
+					State::Closed => panic!("already finished"),
+				}
+			}
+		}
+	}
+}
+
+#[cfg(feature = "nightly-async")]
+use std::{
+	pin::Pin,
+	task,
+	future::Future,
+	task::Poll,
+};
+
+#[cfg(feature = "nightly-async")]
+impl<F: Unpin + 'static> Future for AsyncReadPooled<F> {
+	type Output = Result<(Option<PooledBuf>, F), AsyncReadPooledError<F>>;
+
+	fn poll(mut self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+		let this: &mut Self = &mut *self;
+		match this.0 {
+			State::Pending(ref mut p) => {
+				match p.poll_async(ctx.waker()) {
+					Poll::Pending => Poll::Pending,
+					Poll::Ready((r, context)) => {
+						this.0 = State::Closed;
+						Poll::Ready(context.with_result(r))
+					}
+				}
+			},
+			_ => {
+				match std::mem::replace(&mut this.0, State::Closed) {
+					State::Pending(_) => unreachable!(),
+					State::InitFailed(e) => Poll::Ready(Err(e)),
+					State::Closed => panic!("already finished"),
+				}
+			}
+		}
+	}
+}
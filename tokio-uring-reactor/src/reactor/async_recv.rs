@@ -0,0 +1,163 @@
+use std::{
+	fmt,
+	io,
+	os::unix::io::AsRawFd,
+};
+
+use crate::{
+	reactor::{
+		BufferPool,
+		Handle,
+		PooledBuf,
+	},
+	registration::{
+		Registration,
+		UringResult,
+	},
+};
+
+/// Stream of chunks received by a multishot recv, in buffers picked from
+/// a `BufferPool`
+///
+/// The kernel operation is re-armed on the next poll after it ended
+/// without reaching end of stream.
+pub struct AsyncRecv<F: 'static> {
+	handle: Handle,
+	pool: BufferPool,
+	file: F,
+	active: bool,
+	eof: bool,
+	registration: Registration<()>,
+}
+
+impl<F: AsRawFd + 'static> AsyncRecv<F> {
+	pub(super) fn new(handle: &Handle, file: F, pool: &BufferPool) -> AsyncRecv<F> {
+		let mut registration = Registration::new(());
+		// recycle buffers of chunks received after the stream was dropped
+		let abandoned_pool = pool.clone();
+		registration.on_abandoned(move |result| {
+			if let Some(bid) = result.flags.buffer_id() {
+				abandoned_pool.release(bid);
+			}
+		});
+
+		AsyncRecv {
+			handle: handle.clone(),
+			pool: pool.clone(),
+			file,
+			active: false,
+			eof: false,
+			registration,
+		}
+	}
+
+	fn arm(&mut self) -> io::Result<()> {
+		let mut im = self.handle.inner_mut()?;
+		self.pool.provide_pending(&mut im.pinned());
+		im.pinned().queue_async_recv_multishot(self.file.as_raw_fd(), self.pool.bgid(), self.registration.to_raw())?;
+		self.active = true;
+		Ok(())
+	}
+}
+
+impl<F: 'static> AsyncRecv<F> {
+	pub fn get_ref(&self) -> &F {
+		&self.file
+	}
+
+	fn received(&mut self, r: UringResult) -> io::Result<Option<PooledBuf>> {
+		// take the buffer even if the recv failed, so it gets recycled
+		let buf = r.flags.buffer_id().map(|bid| self.pool.take(bid, 0));
+		if !r.has_more() {
+			// kernel ended the operation
+			self.active = false;
+		}
+		match (r.io_result()?, buf) {
+			(0, _) => {
+				self.eof = true;
+				Ok(None)
+			},
+			(n, Some(mut buf)) => {
+				buf.set_len(n as usize);
+				Ok(Some(buf))
+			},
+			(_, None) => Err(io::Error::new(io::ErrorKind::Other, "recv completed without buffer")),
+		}
+	}
+}
+
+impl<F: 'static> Drop for AsyncRecv<F> {
+	fn drop(&mut self) {
+		self.registration.handle_abandoned();
+		if !self.active || self.registration.is_finished() {
+			return;
+		}
+		let user_data = self.registration.user_data();
+		if let Ok(mut im) = self.handle.inner_mut() {
+			if let Err(e) = im.pinned().queue_cancel(user_data) {
+				log::warn!("failed to cancel dropped recv: {}", e);
+			}
+		}
+	}
+}
+
+impl<F: 'static> fmt::Debug for AsyncRecv<F> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("AsyncRecv")
+			.field("pool", &self.pool)
+			.field("active", &self.active)
+			.field("eof", &self.eof)
+			.field("registration", &self.registration)
+			.finish()
+	}
+}
+
+impl<F: AsRawFd + 'static> futures::Stream for AsyncRecv<F> {
+	type Item = PooledBuf;
+	type Error = io::Error;
+
+	fn poll(&mut self) -> futures::Poll<Option<Self::Item>, Self::Error> {
+		if self.eof {
+			return Ok(futures::Async::Ready(None));
+		}
+		if !self.active {
+			self.arm()?;
+			self.registration.track();
+			return Ok(futures::Async::NotReady);
+		}
+		match self.registration.poll_stream_and_reset() {
+			futures::Async::NotReady => Ok(futures::Async::NotReady),
+			futures::Async::Ready(r) => Ok(futures::Async::Ready(self.received(r)?)),
+		}
+	}
+}
+
+#[cfg(feature = "nightly-async")]
+use std::{
+	pin::Pin,
+	task::Poll,
+	task::Context,
+};
+
+#[cfg(feature = "nightly-async")]
+impl<F: AsRawFd + Unpin + 'static> futures_core::Stream for AsyncRecv<F> {
+	type Item = io::Result<PooledBuf>;
+
+	fn poll_next(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this: &mut Self = &mut *self;
+		if this.eof {
+			return Poll::Ready(None);
+		}
+		if !this.active {
+			if let Err(e) = this.arm() {
+				return Poll::Ready(Some(Err(e)));
+			}
+			this.registration.track_async(ctx.waker());
+			return Poll::Pending;
+		}
+		match this.registration.poll_stream_and_reset_async(ctx.waker()) {
+			Poll::Pending => Poll::Pending,
+			Poll::Ready(r) => Poll::Ready(this.received(r).transpose()),
+		}
+	}
+}
@@ -0,0 +1,190 @@
+use std::{
+	fmt,
+	io,
+	os::unix::io::{AsRawFd},
+};
+
+use crate::{
+	reactor::{
+		ActiveRegistration,
+		Handle,
+		Inner,
+	},
+	registration::{
+		RawRegistration,
+		Registration,
+	},
+};
+
+// #[non_exhaustive] TODO ?
+pub struct AsyncSpliceError<I, O> {
+	pub error: io::Error,
+	pub file_in: I,
+	pub file_out: O,
+}
+
+impl<I, O> From<AsyncSpliceError<I, O>> for io::Error {
+	fn from(e: AsyncSpliceError<I, O>) -> io::Error {
+		e.error
+	}
+}
+
+struct Context<I: 'static, O: 'static> {
+	file_in: I,
+	file_out: O,
+}
+
+impl<I: 'static, O: 'static> Context<I, O> {
+	fn with_error(self, error: io::Error) -> AsyncSpliceError<I, O> {
+		AsyncSpliceError {
+			error,
+			file_in: self.file_in,
+			file_out: self.file_out,
+		}
+	}
+}
+
+enum State<I: 'static, O: 'static> {
+	Pending(ActiveRegistration<Context<I, O>>),
+	InitFailed(AsyncSpliceError<I, O>),
+	Closed,
+}
+
+impl<I: 'static, O: 'static> fmt::Debug for State<I, O> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			State::Pending(ref p) => f.debug_tuple("Pending").field(p).finish(),
+			State::InitFailed(ref e) => f.debug_tuple("InitFailed").field(&e.error).finish(),
+			State::Closed => f.debug_tuple("Closed").finish(),
+		}
+	}
+}
+
+/// Future for both splice and tee operations
+pub struct AsyncSplice<I: 'static, O: 'static>(State<I, O>);
+
+impl<I, O> AsyncSplice<I, O> {
+	pub(super) fn new(handle: &Handle, file_in: I, off_in: Option<u64>, file_out: O, off_out: Option<u64>, len: u32) -> AsyncSplice<I, O>
+	where
+		I: AsRawFd + 'static,
+		O: AsRawFd + 'static,
+	{
+		let fd_in = file_in.as_raw_fd();
+		let fd_out = file_out.as_raw_fd();
+		Self::start(handle, file_in, file_out, |inner, reg| {
+			inner.queue_async_splice(fd_in, off_in, fd_out, off_out, len, reg)
+		})
+	}
+
+	pub(super) fn new_tee(handle: &Handle, file_in: I, file_out: O, len: u32) -> AsyncSplice<I, O>
+	where
+		I: AsRawFd + 'static,
+		O: AsRawFd + 'static,
+	{
+		let fd_in = file_in.as_raw_fd();
+		let fd_out = file_out.as_raw_fd();
+		Self::start(handle, file_in, file_out, |inner, reg| {
+			inner.queue_async_tee(fd_in, fd_out, len, reg)
+		})
+	}
+
+	fn start<Q>(handle: &Handle, file_in: I, file_out: O, queue: Q) -> AsyncSplice<I, O>
+	where
+		Q: FnOnce(&mut Inner, RawRegistration) -> io::Result<()>,
+	{
+		let context = Context {
+			file_in,
+			file_out,
+		};
+
+		let mut im = match handle.inner_mut() {
+			Err(e) => return AsyncSplice(State::InitFailed(context.with_error(e))),
+			Ok(im) => im,
+		};
+
+		// the registration keeps the files open until the operation
+		// completed
+		let reg = Registration::new(context);
+		let queue_result = queue(&mut im.pinned(), reg.to_raw());
+		if let Err(e) = queue_result {
+			let context = reg.abort().expect("registration context");
+			return AsyncSplice(State::InitFailed(context.with_error(e)));
+		}
+		AsyncSplice(State::Pending(ActiveRegistration::new(handle, reg)))
+	}
+}
+
+impl<I: 'static, O: 'static> fmt::Debug for AsyncSplice<I, O> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("AsyncSplice").field(&self.0).finish()
+	}
+}
+
+impl<I: 'static, O: 'static> futures::Future for AsyncSplice<I, O> {
+	type Item = (usize, I, O);
+	type Error = AsyncSpliceError<I, O>;
+
+	fn poll(&mut self) -> futures::Poll<Self::Item, Self::Error> {
+		match self.0 {
+			State::Pending(ref mut p) => {
+				match p.poll() {
+					futures::Async::NotReady => Ok(futures::Async::NotReady),
+					futures::Async::Ready((r, context)) => {
+						let result = match r.io_result() {
+							Err(e) => Err(context.with_error(e)),
+							Ok(n) => Ok(futures::Async::Ready((n as usize, context.file_in, context.file_out))),
+						};
+						self.0 = State::Closed;
+						result
+					}
+				}
+			},
+			_ => {
+				match std::mem::replace(&mut self.0, State::Closed) {
+					State::Pending(_) => unreachable!(),
+					State::InitFailed(e) => Err(e),
+					State::Closed => panic!("already finished"),
+				}
+			}
+		}
+	}
+}
+
+#[cfg(feature = "nightly-async")]
+use std::{
+	pin::Pin,
+	task,
+	future::Future,
+	task::Poll,
+};
+
+#[cfg(feature = "nightly-async")]
+impl<I: Unpin + 'static, O: Unpin + 'static> Future for AsyncSplice<I, O> {
+	type Output = Result<(usize, I, O), AsyncSpliceError<I, O>>;
+
+	fn poll(mut self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+		let this: &mut Self = &mut *self;
+		match this.0 {
+			State::Pending(ref mut p) => {
+				match p.poll_async(ctx.waker()) {
+					Poll::Pending => Poll::Pending,
+					Poll::Ready((r, context)) => {
+						let result = match r.io_result() {
+							Err(e) => Err(context.with_error(e)),
+							Ok(n) => Ok((n as usize, context.file_in, context.file_out)),
+						};
+						this.0 = State::Closed;
+						Poll::Ready(result)
+					}
+				}
+			},
+			_ => {
+				match std::mem::replace(&mut this.0, State::Closed) {
+					State::Pending(_) => unreachable!(),
+					State::InitFailed(e) => Poll::Ready(Err(e)),
+					State::Closed => panic!("already finished"),
+				}
+			}
+		}
+	}
+}
@@ -2,15 +2,20 @@ use std::{
 	fmt,
 	io,
 	os::unix::io::{AsRawFd},
+	time::Duration,
 };
 
 use crate::{
+	io::AsFileDescriptor,
 	reactor::{
+		ActiveRegistration,
 		Handle,
+		Inner,
 		iovec_empty,
 		iovec_from,
 	},
 	registration::{
+		RawRegistration,
 		Registration,
 	},
 };
@@ -30,6 +35,7 @@ impl<T, F> From<AsyncWriteError<T, F>> for io::Error {
 
 struct Context<T: 'static, F: 'static> {
 	iovec: [libc::iovec; 1],
+	timeout: Option<io_uring::KernelTimespec>,
 	buffer: T,
 	file: F,
 }
@@ -42,10 +48,18 @@ impl<T: 'static, F: 'static> Context<T, F> {
 			file: self.file,
 		}
 	}
+
+	fn with_result(self, result: io::Result<u32>) -> Result<(usize, T, F), AsyncWriteError<T, F>> {
+		match result {
+			// the registration reports an expired linked timeout as ETIMEDOUT
+			Err(e) => Err(self.with_error(e)),
+			Ok(n) => Ok((n as usize, self.buffer, self.file)),
+		}
+	}
 }
 
 enum State<T: 'static, F: 'static> {
-	Pending(Registration<Context<T, F>>),
+	Pending(ActiveRegistration<Context<T, F>>),
 	InitFailed(AsyncWriteError<T, F>),
 	Closed,
 }
@@ -63,14 +77,25 @@ impl<T: 'static, F: 'static> fmt::Debug for State<T, F> {
 pub struct AsyncWrite<T: 'static, F: 'static>(State<T, F>);
 
 impl<T, F> AsyncWrite<T, F> {
-	pub(super) fn new(handle: &Handle, file: F, offset: u64, buffer: T) -> AsyncWrite<T, F>
+	pub(super) fn new(handle: &Handle, file: F, offset: u64, buffer: T, timeout: Option<Duration>) -> AsyncWrite<T, F>
 	where
 		T: AsRef<[u8]> + 'static,
 		F: AsRawFd + 'static,
 	{
-		let fd = file.as_raw_fd();
+		let fd = io_uring::FileDescriptor::FD(file.as_raw_fd());
+		Self::start(handle, file, buffer, timeout, |inner, d, timeout, reg| {
+			d.iovec[0] = iovec_from(d.buffer.as_ref());
+			inner.queue_async_write(fd, offset, &d.iovec, timeout, reg)
+		})
+	}
+
+	fn start<Q>(handle: &Handle, file: F, buffer: T, timeout: Option<Duration>, queue: Q) -> AsyncWrite<T, F>
+	where
+		Q: FnOnce(&mut Inner, &mut Context<T, F>, Option<*const io_uring::KernelTimespec>, RawRegistration) -> io::Result<()>,
+	{
 		let context = Context {
-			iovec: [ iovec_empty() ], // fill below
+			iovec: [ iovec_empty() ], // filled by `queue` if needed
+			timeout: timeout.map(io_uring::KernelTimespec::from),
 			buffer,
 			file,
 		};
@@ -83,19 +108,31 @@ impl<T, F> AsyncWrite<T, F> {
 		// this "pins" buf, as the data is boxed
 		let mut reg = Registration::new(context);
 		let queue_result = {
-			let iovec = unsafe {
-				let d = reg.data_mut();
-				d.iovec[0] = iovec_from(d.buffer.as_ref());
-				&d.iovec
-			};
-
-			im.pinned().queue_async_write(fd, offset, iovec, reg.to_raw())
+			let d: *mut Context<T, F> = unsafe { reg.data_mut() };
+			let d = unsafe { &mut *d };
+			let timeout = d.timeout.as_ref().map(|t| t as *const _);
+			queue(&mut im.pinned(), d, timeout, reg.to_raw())
 		};
 		if let Err(e) = queue_result {
 			let context = reg.abort().expect("registration context");
 			return AsyncWrite(State::InitFailed(context.with_error(e)));
 		}
-		AsyncWrite(State::Pending(reg))
+		AsyncWrite(State::Pending(ActiveRegistration::new(handle, reg)))
+	}
+}
+
+impl<F> AsyncWrite<io_uring::FixedBuf, F> {
+	pub(super) fn new_fixed(handle: &Handle, file: F, offset: u64, buffer: io_uring::FixedBuf, len: usize, timeout: Option<Duration>) -> AsyncWrite<io_uring::FixedBuf, F>
+	where
+		F: AsFileDescriptor + 'static,
+	{
+		let fd = file.as_file_descriptor();
+		Self::start(handle, file, buffer, timeout, |inner, d, timeout, reg| {
+			if len > d.buffer.len() {
+				return Err(io::Error::new(io::ErrorKind::InvalidInput, "write length exceeds fixed buffer"));
+			}
+			inner.queue_async_write_fixed(fd, offset, &d.buffer, len, timeout, reg)
+		})
 	}
 }
 
@@ -115,11 +152,7 @@ impl<T: 'static, F: 'static> futures::Future for AsyncWrite<T, F> {
 				match p.poll() {
 					futures::Async::NotReady => Ok(futures::Async::NotReady),
 					futures::Async::Ready((r, context)) => {
-						let result = if r.result < 0 {
-							Err(context.with_error(io::Error::from_raw_os_error(-r.result)))
-						} else {
-							Ok(futures::Async::Ready((r.result as usize, context.buffer, context.file)))
-						};
+						let result = context.with_result(r.io_result()).map(futures::Async::Ready);
 						std::mem::replace(&mut self.0, State::Closed);
 						result
 					}
@@ -155,11 +188,7 @@ impl<T: Unpin + 'static, F: Unpin + 'static> Future for AsyncWrite<T, F> {
 				match p.poll_async(ctx.waker()) {
 					Poll::Pending => Poll::Pending,
 					Poll::Ready((r, context)) => {
-						let result = if r.result < 0 {
-							Err(context.with_error(io::Error::from_raw_os_error(-r.result)))
-						} else {
-							Ok((r.result as usize, context.buffer, context.file))
-						};
+						let result = context.with_result(r.io_result());
 						std::mem::replace(&mut this.0, State::Closed);
 						Poll::Ready(result)
 					}
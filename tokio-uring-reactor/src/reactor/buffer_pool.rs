@@ -0,0 +1,304 @@
+use std::{
+	cell::RefCell,
+	fmt,
+	io,
+	ops::{
+		Deref,
+		DerefMut,
+	},
+	rc::Rc,
+};
+
+use crate::reactor::{
+	Handle,
+	Inner,
+};
+
+enum Buffers {
+	Ring(io_uring::BufferRing),
+	// kernel before 5.19: buffers are handed back with `PROVIDE_BUFFERS`
+	Provided(*mut [u8]),
+}
+
+// groups of dropped pools still provided to the kernel; shared with the
+// reactor, which removes them when it parks next time.
+//
+// pools can be dropped while the reactor handles completions, so their
+// drop must not access the reactor.
+#[derive(Clone, Default)]
+pub(super) struct RetiredGroups(Rc<RefCell<Vec<(u16, u16)>>>);
+
+impl RetiredGroups {
+	fn retire(&self, bgid: u16, count: u16) {
+		self.0.borrow_mut().push((bgid, count));
+	}
+
+	pub(super) fn remove_all(&self, inner: &mut Inner) {
+		let mut groups = self.0.borrow_mut();
+		while let Some(&(bgid, count)) = groups.last() {
+			if let Err(e) = inner.queue_remove_buffers(count as u32, bgid) {
+				// retry later
+				log::debug!("failed to remove buffer group {}: {}", bgid, e);
+				return;
+			}
+			groups.pop();
+		}
+	}
+}
+
+struct Shared {
+	handle: Handle,
+	retired: RetiredGroups,
+	bgid: u16,
+	count: u16,
+	buf_len: usize,
+	buffers: RefCell<Buffers>,
+	// consumed provided buffers not handed back to the kernel yet
+	pending: RefCell<Vec<u16>>,
+}
+
+impl Shared {
+	fn buf_ptr(&self, bid: u16) -> *mut u8 {
+		match *self.buffers.borrow() {
+			Buffers::Ring(ref ring) => ring.buffer(bid),
+			Buffers::Provided(memory) => {
+				assert!(bid < self.count);
+				(memory as *mut u8 as usize + (bid as usize) * self.buf_len) as *mut u8
+			},
+		}
+	}
+
+	// buffer `bid` was consumed by a completed operation and isn't used
+	// anymore
+	fn recycle(&self, bid: u16) {
+		self.release(bid);
+		if self.pending.borrow().is_empty() {
+			return;
+		}
+		match self.handle.inner_mut() {
+			Ok(mut im) => self.provide_pending(&mut im.pinned()),
+			Err(e) => {
+				// the reactor is gone; nothing can select buffers anymore
+				log::debug!("failed to provide buffers in group {}: {}", self.bgid, e);
+			},
+		}
+	}
+
+	// like `recycle`, but doesn't access the reactor; provided buffers
+	// are handed back with the next `recycle` or `provide_pending`
+	fn release(&self, bid: u16) {
+		match *self.buffers.borrow_mut() {
+			Buffers::Ring(ref mut ring) => unsafe { ring.recycle(bid) },
+			Buffers::Provided(_) => self.pending.borrow_mut().push(bid),
+		}
+	}
+
+	fn provide_pending(&self, inner: &mut Inner) {
+		let mut pending = self.pending.borrow_mut();
+		while let Some(&bid) = pending.last() {
+			let addr = self.buf_ptr(bid);
+			if let Err(e) = inner.queue_provide_buffers(addr, self.buf_len as u32, 1, self.bgid, bid) {
+				// retry later
+				log::debug!("failed to provide buffer {} in group {}: {}", bid, self.bgid, e);
+				return;
+			}
+			pending.pop();
+		}
+	}
+}
+
+impl Drop for Shared {
+	fn drop(&mut self) {
+		match *self.buffers.get_mut() {
+			Buffers::Ring(_) => (), // unregistered by `BufferRing`
+			Buffers::Provided(memory) => {
+				// the kernel only writes to selected buffers, and all
+				// operations using the group keep the pool alive
+				self.retired.retire(self.bgid, self.count);
+				drop(unsafe { Box::from_raw(memory) });
+			},
+		}
+	}
+}
+
+/// Pool of buffers the kernel picks from when an operation needs one
+///
+/// Uses a registered buffer ring (`IORING_REGISTER_PBUF_RING`) and
+/// falls back to `PROVIDE_BUFFERS` on older kernels. Reads through
+/// `Handle::async_read_pooled` don't tie up a buffer while they wait
+/// for data; the completion returns the chosen buffer as `PooledBuf`,
+/// which hands it back to the pool on drop.
+///
+/// If all buffers are in use, reads fail with `ENOBUFS`.
+#[derive(Clone)]
+pub struct BufferPool(Rc<Shared>);
+
+impl BufferPool {
+	/// `count` buffers of `buf_len` bytes each; `count` must be a power
+	/// of 2.
+	pub fn new(handle: &Handle, count: u16, buf_len: usize) -> io::Result<BufferPool> {
+		if !count.is_power_of_two() {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "buffer count must be a power of 2"));
+		}
+		if 0 == buf_len || buf_len > u32::max_value() as usize {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid buffer length"));
+		}
+
+		let mut im = handle.inner_mut()?;
+		let inner = im.pinned().get_mut();
+		let bgid = inner.next_buffer_group;
+
+		let buffers = match io_uring::BufferRing::register(inner.uring.file(), bgid, count, buf_len) {
+			Ok(ring) => Buffers::Ring(ring),
+			Err(ref e) if e.raw_os_error() == Some(libc::EINVAL) && inner.supports(io_uring::Operation::ProvideBuffers) => {
+				let total = buf_len.checked_mul(count as usize)
+					.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "buffers too large"))?;
+				let memory = Box::into_raw(vec![0u8; total].into_boxed_slice());
+				// until the pool is built nothing removes the buffers again
+				if let Err(e) = inner.queue_provide_buffers(memory as *mut u8, buf_len as u32, count as u32, bgid, 0) {
+					drop(unsafe { Box::from_raw(memory) });
+					return Err(e);
+				}
+				Buffers::Provided(memory)
+			},
+			Err(e) => return Err(e),
+		};
+		inner.next_buffer_group = bgid.wrapping_add(1);
+
+		Ok(BufferPool(Rc::new(Shared {
+			handle: handle.clone(),
+			retired: inner.retired_buffer_groups.clone(),
+			bgid,
+			count,
+			buf_len,
+			buffers: RefCell::new(buffers),
+			pending: RefCell::new(Vec::new()),
+		})))
+	}
+
+	/// reactor the pool is registered with
+	pub fn handle(&self) -> &Handle {
+		&self.0.handle
+	}
+
+	/// buffer group id the kernel selects from
+	pub fn bgid(&self) -> u16 {
+		self.0.bgid
+	}
+
+	/// number of buffers
+	pub fn count(&self) -> u16 {
+		self.0.count
+	}
+
+	/// size of each buffer
+	pub fn buf_len(&self) -> usize {
+		self.0.buf_len
+	}
+
+	// hand back buffers released while completions were processed; call
+	// before queueing operations selecting from the pool
+	pub(super) fn provide_pending(&self, inner: &mut Inner) {
+		self.0.provide_pending(inner)
+	}
+
+	// buffer `bid` the kernel picked for an operation whose result was
+	// abandoned; doesn't access the reactor
+	pub(super) fn release(&self, bid: u16) {
+		self.0.release(bid)
+	}
+
+	// take ownership of buffer `bid` the kernel picked for a completed
+	// operation that transferred `len` bytes
+	pub(super) fn take(&self, bid: u16, len: usize) -> PooledBuf {
+		assert!(len <= self.0.buf_len);
+		PooledBuf {
+			shared: self.0.clone(),
+			bid,
+			len,
+		}
+	}
+}
+
+impl fmt::Debug for BufferPool {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let ring = match *self.0.buffers.borrow() {
+			Buffers::Ring(_) => true,
+			Buffers::Provided(_) => false,
+		};
+		f.debug_struct("BufferPool")
+			.field("bgid", &self.0.bgid)
+			.field("count", &self.0.count)
+			.field("buf_len", &self.0.buf_len)
+			.field("ring", &ring)
+			.finish()
+	}
+}
+
+/// Buffer the kernel picked from a `BufferPool`; returned to the pool on
+/// drop
+///
+/// Dereferences to the received data.
+pub struct PooledBuf {
+	shared: Rc<Shared>,
+	bid: u16,
+	len: usize,
+}
+
+impl PooledBuf {
+	/// buffer id in the pool
+	pub fn bid(&self) -> u16 {
+		self.bid
+	}
+
+	/// size of the complete buffer
+	pub fn capacity(&self) -> usize {
+		self.shared.buf_len
+	}
+
+	pub(super) fn set_len(&mut self, len: usize) {
+		assert!(len <= self.shared.buf_len);
+		self.len = len;
+	}
+}
+
+impl Deref for PooledBuf {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		unsafe { std::slice::from_raw_parts(self.shared.buf_ptr(self.bid), self.len) }
+	}
+}
+
+impl DerefMut for PooledBuf {
+	fn deref_mut(&mut self) -> &mut [u8] {
+		unsafe { std::slice::from_raw_parts_mut(self.shared.buf_ptr(self.bid), self.len) }
+	}
+}
+
+impl AsRef<[u8]> for PooledBuf {
+	fn as_ref(&self) -> &[u8] {
+		self
+	}
+}
+
+impl AsMut<[u8]> for PooledBuf {
+	fn as_mut(&mut self) -> &mut [u8] {
+		self
+	}
+}
+
+impl Drop for PooledBuf {
+	fn drop(&mut self) {
+		self.shared.recycle(self.bid);
+	}
+}
+
+impl fmt::Debug for PooledBuf {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("PooledBuf")
+			.field("bid", &self.bid)
+			.field("len", &self.len)
+			.finish()
+	}
+}
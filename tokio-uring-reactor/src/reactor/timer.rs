@@ -0,0 +1,399 @@
+use std::{
+	fmt,
+	io,
+	time::Duration,
+};
+
+use crate::{
+	reactor::{
+		ActiveRegistration,
+		Handle,
+	},
+	registration::{
+		Registration,
+		UringResult,
+	},
+};
+
+// all timers use absolute CLOCK_MONOTONIC deadlines (the kernel default
+// clock for IORING_TIMEOUT_ABS)
+fn monotonic_now() -> Duration {
+	let mut ts = libc::timespec {
+		tv_sec: 0,
+		tv_nsec: 0,
+	};
+	unsafe {
+		libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts);
+	}
+	Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+struct Context {
+	// only needs to live until submission, but the registration is the
+	// easiest place to keep it pinned.
+	timespec: io_uring::KernelTimespec,
+}
+
+enum State {
+	Pending(ActiveRegistration<Context>),
+	InitFailed(io::Error),
+	Closed,
+}
+
+impl fmt::Debug for State {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			State::Pending(ref p) => f.debug_tuple("Pending").field(p).finish(),
+			State::InitFailed(ref e) => f.debug_tuple("InitFailed").field(e).finish(),
+			State::Closed => f.debug_tuple("Closed").finish(),
+		}
+	}
+}
+
+/// Future completing after a deadline, backed by a kernel timeout
+pub struct Sleep(State);
+
+impl Sleep {
+	// `deadline` is an absolute CLOCK_MONOTONIC time
+	fn new_at(handle: &Handle, deadline: Duration) -> Sleep {
+		let mut im = match handle.inner_mut() {
+			Err(e) => return Sleep(State::InitFailed(e)),
+			Ok(im) => im,
+		};
+
+		let mut reg = Registration::new(Context {
+			timespec: deadline.into(),
+		});
+		let queue_result = {
+			let timespec = unsafe { &reg.data_mut().timespec };
+			im.pinned().queue_timeout(timespec, io_uring::TimeoutFlags::ABS, reg.to_raw())
+		};
+		if let Err(e) = queue_result {
+			reg.abort().expect("registration context");
+			return Sleep(State::InitFailed(e));
+		}
+		Sleep(State::Pending(ActiveRegistration::new(handle, reg)))
+	}
+
+	pub(super) fn new(handle: &Handle, duration: Duration) -> Sleep {
+		Self::new_at(handle, monotonic_now() + duration)
+	}
+}
+
+fn timeout_result(result: UringResult) -> io::Result<()> {
+	match result.io_result() {
+		Err(ref e) if e.raw_os_error() == Some(libc::ETIME) => Ok(()),
+		Err(e) => Err(e),
+		Ok(_) => Ok(()),
+	}
+}
+
+impl fmt::Debug for Sleep {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("Sleep").field(&self.0).finish()
+	}
+}
+
+impl futures::Future for Sleep {
+	type Item = ();
+	type Error = io::Error;
+
+	fn poll(&mut self) -> futures::Poll<Self::Item, Self::Error> {
+		match self.0 {
+			State::Pending(ref mut p) => {
+				match p.poll() {
+					futures::Async::NotReady => Ok(futures::Async::NotReady),
+					futures::Async::Ready((r, _context)) => {
+						self.0 = State::Closed;
+						timeout_result(r)?;
+						Ok(futures::Async::Ready(()))
+					}
+				}
+			},
+			_ => {
+				match std::mem::replace(&mut self.0, State::Closed) {
+					State::Pending(_) => unreachable!(),
+					State::InitFailed(e) => Err(e),
+					State::Closed => panic!("already finished"),
+				}
+			}
+		}
+	}
+}
+
+/// Stream yielding once every period, backed by kernel timeouts
+///
+/// Deadlines are absolute, so a late consumer doesn't accumulate drift;
+/// missed ticks are yielded immediately.
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct Interval {
+	handle: Handle,
+	period: Duration,
+	next: Duration,
+	sleep: Sleep,
+}
+
+impl Interval {
+	pub(super) fn new(handle: &Handle, period: Duration) -> Interval {
+		let next = monotonic_now() + period;
+		Interval {
+			handle: handle.clone(),
+			period,
+			next,
+			sleep: Sleep::new_at(handle, next),
+		}
+	}
+
+	fn rearm(&mut self) {
+		self.next += self.period;
+		self.sleep = Sleep::new_at(&self.handle, self.next);
+	}
+}
+
+impl futures::Stream for Interval {
+	type Item = ();
+	type Error = io::Error;
+
+	fn poll(&mut self) -> futures::Poll<Option<Self::Item>, Self::Error> {
+		match futures::Future::poll(&mut self.sleep) {
+			Ok(futures::Async::NotReady) => Ok(futures::Async::NotReady),
+			Ok(futures::Async::Ready(())) => {
+				self.rearm();
+				Ok(futures::Async::Ready(Some(())))
+			},
+			Err(e) => {
+				self.rearm();
+				Err(e)
+			},
+		}
+	}
+}
+
+pub enum TimeoutError<E> {
+	/// deadline passed before the inner future completed
+	Elapsed,
+	/// inner future failed
+	Inner(E),
+	/// kernel timeout failed
+	Timer(io::Error),
+}
+
+impl<E> From<TimeoutError<E>> for io::Error
+where
+	E: Into<io::Error>,
+{
+	fn from(e: TimeoutError<E>) -> io::Error {
+		match e {
+			TimeoutError::Elapsed => io::Error::new(io::ErrorKind::TimedOut, "async operation timed out"),
+			TimeoutError::Inner(e) => e.into(),
+			TimeoutError::Timer(e) => e,
+		}
+	}
+}
+
+impl<E: fmt::Debug> fmt::Debug for TimeoutError<E> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			TimeoutError::Elapsed => f.debug_tuple("Elapsed").finish(),
+			TimeoutError::Inner(ref e) => f.debug_tuple("Inner").field(e).finish(),
+			TimeoutError::Timer(ref e) => f.debug_tuple("Timer").field(e).finish(),
+		}
+	}
+}
+
+/// Future failing with `TimeoutError::Elapsed` if the inner future
+/// doesn't complete in time
+///
+/// The deadline starts when the `Timeout` is created. Wrapping a
+/// stream limits the time between items instead: the deadline restarts
+/// after each item.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Timeout<F> {
+	inner: F,
+	handle: Handle,
+	timeout: Duration,
+	sleep: Sleep,
+}
+
+impl<F> Timeout<F> {
+	pub(super) fn new(handle: &Handle, inner: F, timeout: Duration) -> Timeout<F> {
+		Timeout {
+			inner,
+			handle: handle.clone(),
+			timeout,
+			sleep: Sleep::new(handle, timeout),
+		}
+	}
+
+	// restart deadline (streams)
+	fn reset(&mut self) {
+		self.sleep = Sleep::new(&self.handle, self.timeout);
+	}
+
+	pub fn get_ref(&self) -> &F {
+		&self.inner
+	}
+
+	pub fn get_mut(&mut self) -> &mut F {
+		&mut self.inner
+	}
+
+	pub fn into_inner(self) -> F {
+		self.inner
+	}
+}
+
+impl<F: futures::Future> futures::Future for Timeout<F> {
+	type Item = F::Item;
+	type Error = TimeoutError<F::Error>;
+
+	fn poll(&mut self) -> futures::Poll<Self::Item, Self::Error> {
+		match self.inner.poll() {
+			Ok(futures::Async::NotReady) => (),
+			Ok(futures::Async::Ready(r)) => return Ok(futures::Async::Ready(r)),
+			Err(e) => return Err(TimeoutError::Inner(e)),
+		}
+		match futures::Future::poll(&mut self.sleep) {
+			Ok(futures::Async::NotReady) => Ok(futures::Async::NotReady),
+			Ok(futures::Async::Ready(())) => Err(TimeoutError::Elapsed),
+			Err(e) => Err(TimeoutError::Timer(e)),
+		}
+	}
+}
+
+impl<S: futures::Stream> futures::Stream for Timeout<S> {
+	type Item = S::Item;
+	type Error = TimeoutError<S::Error>;
+
+	fn poll(&mut self) -> futures::Poll<Option<Self::Item>, Self::Error> {
+		match self.inner.poll() {
+			Ok(futures::Async::NotReady) => (),
+			Ok(futures::Async::Ready(None)) => return Ok(futures::Async::Ready(None)),
+			Ok(futures::Async::Ready(Some(r))) => {
+				self.reset();
+				return Ok(futures::Async::Ready(Some(r)));
+			},
+			Err(e) => {
+				self.reset();
+				return Err(TimeoutError::Inner(e));
+			},
+		}
+		match futures::Future::poll(&mut self.sleep) {
+			Ok(futures::Async::NotReady) => Ok(futures::Async::NotReady),
+			Ok(futures::Async::Ready(())) => {
+				self.reset();
+				Err(TimeoutError::Elapsed)
+			},
+			Err(e) => {
+				self.reset();
+				Err(TimeoutError::Timer(e))
+			},
+		}
+	}
+}
+
+#[cfg(feature = "nightly-async")]
+use std::{
+	pin::Pin,
+	task,
+	future::Future,
+	task::Poll,
+};
+
+#[cfg(feature = "nightly-async")]
+impl Future for Sleep {
+	type Output = io::Result<()>;
+
+	fn poll(mut self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+		let this: &mut Self = &mut *self;
+		match this.0 {
+			State::Pending(ref mut p) => {
+				match p.poll_async(ctx.waker()) {
+					Poll::Pending => Poll::Pending,
+					Poll::Ready((r, _context)) => {
+						this.0 = State::Closed;
+						Poll::Ready(timeout_result(r))
+					}
+				}
+			},
+			_ => {
+				match std::mem::replace(&mut this.0, State::Closed) {
+					State::Pending(_) => unreachable!(),
+					State::InitFailed(e) => Poll::Ready(Err(e)),
+					State::Closed => panic!("already finished"),
+				}
+			}
+		}
+	}
+}
+
+#[cfg(feature = "nightly-async")]
+impl futures_core::Stream for Interval {
+	type Item = io::Result<()>;
+
+	fn poll_next(mut self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Option<Self::Item>> {
+		let this: &mut Self = &mut *self;
+		match Pin::new(&mut this.sleep).poll(ctx) {
+			Poll::Pending => Poll::Pending,
+			Poll::Ready(r) => {
+				this.rearm();
+				Poll::Ready(Some(r))
+			},
+		}
+	}
+}
+
+#[cfg(feature = "nightly-async")]
+impl<I, E, F> Future for Timeout<F>
+where
+	F: Future<Output = Result<I, E>>,
+{
+	type Output = Result<I, TimeoutError<E>>;
+
+	fn poll(self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Self::Output> {
+		// must not move this.inner
+		let this = unsafe { self.get_unchecked_mut() };
+		match unsafe { Pin::new_unchecked(&mut this.inner) }.poll(ctx) {
+			Poll::Pending => (),
+			Poll::Ready(r) => return Poll::Ready(r.map_err(TimeoutError::Inner)),
+		}
+		match Pin::new(&mut this.sleep).poll(ctx) {
+			Poll::Pending => Poll::Pending,
+			Poll::Ready(Ok(())) => Poll::Ready(Err(TimeoutError::Elapsed)),
+			Poll::Ready(Err(e)) => Poll::Ready(Err(TimeoutError::Timer(e))),
+		}
+	}
+}
+
+#[cfg(feature = "nightly-async")]
+impl<I, E, S> futures_core::Stream for Timeout<S>
+where
+	S: futures_core::Stream<Item = Result<I, E>>,
+{
+	type Item = Result<I, TimeoutError<E>>;
+
+	fn poll_next(self: Pin<&mut Self>, ctx: &mut task::Context<'_>) -> Poll<Option<Self::Item>> {
+		// must not move this.inner
+		let this = unsafe { self.get_unchecked_mut() };
+		match unsafe { Pin::new_unchecked(&mut this.inner) }.poll_next(ctx) {
+			Poll::Pending => (),
+			Poll::Ready(None) => return Poll::Ready(None),
+			Poll::Ready(Some(r)) => {
+				this.reset();
+				return Poll::Ready(Some(r.map_err(TimeoutError::Inner)));
+			},
+		}
+		match Pin::new(&mut this.sleep).poll(ctx) {
+			Poll::Pending => Poll::Pending,
+			Poll::Ready(r) => {
+				this.reset();
+				Poll::Ready(Some(Err(match r {
+					Ok(()) => TimeoutError::Elapsed,
+					Err(e) => TimeoutError::Timer(e),
+				})))
+			},
+		}
+	}
+}
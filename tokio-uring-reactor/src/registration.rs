@@ -1,6 +1,8 @@
 use std::any::Any;
 use std::cell::UnsafeCell;
+use std::collections::VecDeque;
 use std::fmt;
+use std::io;
 use std::marker::PhantomData;
 use std::rc::Rc;
 
@@ -46,15 +48,61 @@ impl Default for CompatWaker {
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
 pub struct UringResult {
 	pub result: i32,
-	pub flags: u32,
+	pub flags: io_uring::CompletionFlags,
 }
 
+impl UringResult {
+	/// negative results are (negated) errno values
+	pub fn io_result(&self) -> io::Result<u32> {
+		if self.result < 0 {
+			Err(io::Error::from_raw_os_error(-self.result))
+		} else {
+			Ok(self.result as u32)
+		}
+	}
+
+	/// multishot operation stays active and will post more results
+	pub fn has_more(&self) -> bool {
+		self.flags.contains(io_uring::CompletionFlags::F_MORE)
+	}
+}
+
+type AbandonedHandler = Box<dyn FnMut(UringResult)>;
+
 #[derive(Default)]
 struct Inner {
 	result: UringResult,
 	finished: bool,
+	// multishot results (flagged `F_MORE`) not polled yet; the final
+	// result goes into `result`
+	more: VecDeque<UringResult>,
+	// the linked timeout hasn't completed yet; the final result waits
+	// for it (`result_pending`)
+	link_timeout_pending: bool,
+	result_pending: bool,
+	// the linked timeout expired (`-ETIME`)
+	timed_out: bool,
 	waker: UnsafeCell<CompatWaker>,
 	data: Option<Box<dyn Any>>,
+	abandoned: Option<AbandonedHandler>,
+}
+
+impl Inner {
+	// pass results nobody will poll to the handler
+	fn handle_abandoned(&mut self) {
+		let handler = match self.abandoned {
+			Some(ref mut handler) => handler,
+			None => return,
+		};
+		for result in self.more.drain(..) {
+			handler(result);
+		}
+		// single-shot results are consumed with the data; stream results
+		// reset `finished`
+		if self.finished && self.data.take().is_some() {
+			handler(self.result);
+		}
+	}
 }
 
 pub struct RawRegistration {
@@ -65,18 +113,85 @@ impl RawRegistration {
 	pub fn notify(&mut self, result: UringResult) {
 		let inner = unsafe { &mut *self.inner.get() };
 		assert!(!inner.finished);
-		inner.finished = true;
-		inner.result = result;
+		if inner.link_timeout_pending && !result.has_more() {
+			// only the linked timeout knows whether it canceled the
+			// operation
+			assert!(!inner.result_pending);
+			inner.result = result;
+			inner.result_pending = true;
+			return;
+		}
+		self.finish(result);
+	}
+
+	/// result of the `LINK_TIMEOUT` entry registered with
+	/// `link_timeout_user_data`
+	pub fn notify_link_timeout(&mut self, result: UringResult) {
+		let inner = unsafe { &mut *self.inner.get() };
+		assert!(inner.link_timeout_pending);
+		inner.link_timeout_pending = false;
+		inner.timed_out = result.result == -libc::ETIME;
+		if inner.result_pending {
+			inner.result_pending = false;
+			let result = inner.result;
+			self.finish(result);
+		}
+	}
+
+	fn finish(&mut self, mut result: UringResult) {
+		let inner = unsafe { &mut *self.inner.get() };
+		if inner.timed_out && (result.result == -libc::ECANCELED || result.result == -libc::EINTR) {
+			// canceled by the linked timeout (EINTR if it was already
+			// running)
+			result.result = -libc::ETIMEDOUT;
+		}
+		if 1 == Rc::strong_count(&self.inner) {
+			// `Registration` is gone, nobody will poll the result
+			if let Some(ref mut handler) = inner.abandoned {
+				handler(result);
+			}
+			inner.finished = !result.has_more();
+			return;
+		}
+		if result.has_more() {
+			// multishot operation stays active
+			inner.more.push_back(result);
+		} else {
+			inner.finished = true;
+			inner.result = result;
+		}
 		let waker = unsafe { &mut *inner.waker.get() };
 		waker.notify();
 	}
 
+	pub fn user_data(&self) -> u64 {
+		let user_data = &(*self.inner) as *const UnsafeCell<Inner> as usize as u64;
+		assert!(user_data != 0 && user_data & 0x1 == 0);
+		user_data
+	}
+
 	pub unsafe fn into_user_data(self) -> u64 {
 		let user_data = Rc::into_raw(self.inner) as usize as u64;
 		assert!(user_data != 0 && user_data & 0x1 == 0);
 		user_data
 	}
 
+	/// user_data for a `LINK_TIMEOUT` entry linked to this operation;
+	/// the completion must be passed to `notify_link_timeout` (after
+	/// `from_user_data(user_data & !0x1)`).
+	///
+	/// The operation only finishes after both completions arrived.
+	pub unsafe fn link_timeout_user_data(&self) -> u64 {
+		let inner = &mut *self.inner.get();
+		assert!(!inner.link_timeout_pending);
+		inner.link_timeout_pending = true;
+		let user_data = Rc::into_raw(self.inner.clone()) as usize as u64;
+		// the pointer is aligned, so this can't clash with the internal
+		// (small, odd) reactor events
+		assert!(user_data > 0x7 && user_data & 0x1 == 0);
+		user_data | 0x1
+	}
+
 	pub unsafe fn from_user_data(data: u64) -> Self {
 		RawRegistration {
 			inner: Rc::from_raw(data as usize as *const UnsafeCell<Inner>),
@@ -149,6 +264,33 @@ impl<T: 'static> Registration<T> {
 		}
 	}
 
+	/// pass results to `handler` that are never polled
+	///
+	/// For results that need cleanup (e.g. buffers the kernel picked or
+	/// accepted fds). The handler might run while completions are
+	/// processed; it must not access the reactor.
+	pub fn on_abandoned<F>(&mut self, handler: F)
+	where
+		F: FnMut(UringResult) + 'static,
+	{
+		let inner = unsafe { &mut *self.inner.get() };
+		inner.abandoned = Some(Box::new(handler));
+	}
+
+	/// pass results that arrived but weren't polled to the
+	/// `on_abandoned` handler; call before dropping the registration
+	pub fn handle_abandoned(&mut self) {
+		let inner = unsafe { &mut *self.inner.get() };
+		inner.handle_abandoned();
+	}
+
+	/// whether the kernel completed the operation (for multishot
+	/// operations: posted the final result)
+	pub fn is_finished(&self) -> bool {
+		let inner = unsafe { &*self.inner.get() };
+		inner.finished
+	}
+
 	pub fn abort(self) -> Option<T> {
 		Some(*Rc::try_unwrap(self.inner).ok()?.into_inner().data.expect("data").downcast::<T>().expect("type"))
 	}
@@ -176,8 +318,14 @@ impl Registration<()> {
 	// if there is no data we can easily reuse the registration; the
 	// caller must track though whether the registration is active or
 	// not.
+	//
+	// multishot results (flagged `F_MORE`) are returned in order before
+	// the final one, which resets the registration.
 	pub fn poll_stream_and_reset(&mut self) -> futures::Async<UringResult> {
 		let inner = unsafe { &mut *self.inner.get() };
+		if let Some(result) = inner.more.pop_front() {
+			return futures::Async::Ready(result);
+		}
 		if inner.finished {
 			inner.finished = false; // reset
 			futures::Async::Ready(inner.result)
@@ -191,6 +339,9 @@ impl Registration<()> {
 	#[cfg(feature = "nightly-async")]
 	pub fn poll_stream_and_reset_async(&mut self, waker: &Waker) -> Poll<UringResult> {
 		let inner = unsafe { &mut *self.inner.get() };
+		if let Some(result) = inner.more.pop_front() {
+			return Poll::Ready(result);
+		}
 		if inner.finished {
 			inner.finished = false; // reset
 			Poll::Ready(inner.result)
@@ -215,3 +366,59 @@ impl<T> fmt::Debug for Registration<T> {
 			.finish()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn result(result: i32) -> UringResult {
+		UringResult {
+			result,
+			.. UringResult::default()
+		}
+	}
+
+	// completions of an operation with linked timeout, in the given order
+	fn complete(op: i32, timeout: i32, timeout_first: bool) -> i32 {
+		let mut reg = Registration::new(());
+		let mut raw = reg.to_raw();
+		let mut link = unsafe { RawRegistration::from_user_data(raw.link_timeout_user_data() & !0x1) };
+		if timeout_first {
+			link.notify_link_timeout(result(timeout));
+			drop(link);
+			raw.notify(result(op));
+		} else {
+			raw.notify(result(op));
+			drop(raw);
+			assert!(!reg.is_finished());
+			link.notify_link_timeout(result(timeout));
+		}
+		assert!(reg.is_finished());
+		match reg.poll() {
+			futures::Async::Ready((r, ())) => r.result,
+			futures::Async::NotReady => panic!("not ready"),
+		}
+	}
+
+	#[test]
+	fn link_timeout_expired() {
+		for &timeout_first in &[false, true] {
+			assert_eq!(complete(-libc::ECANCELED, -libc::ETIME, timeout_first), -libc::ETIMEDOUT);
+			// timeout fired while the operation was running
+			assert_eq!(complete(-libc::EINTR, -libc::ETIME, timeout_first), -libc::ETIMEDOUT);
+			// operation completed anyway
+			assert_eq!(complete(5, -libc::ETIME, timeout_first), 5);
+		}
+	}
+
+	#[test]
+	fn link_timeout_canceled() {
+		for &timeout_first in &[false, true] {
+			// operation completed, timeout got removed
+			assert_eq!(complete(5, -libc::ECANCELED, timeout_first), 5);
+			// both canceled (e.g. reactor shutdown)
+			assert_eq!(complete(-libc::ECANCELED, -libc::ECANCELED, timeout_first), -libc::ECANCELED);
+			assert_eq!(complete(-libc::EINTR, -libc::ECANCELED, timeout_first), -libc::EINTR);
+		}
+	}
+}
@@ -2,11 +2,13 @@ use std::io;
 use std::net;
 use std::time::Duration;
 use futures::prelude::*;
-use tokio_uring_reactor::io::{
-	SocketRead,
-	SocketWrite,
+use tokio_uring_reactor::{
+	io::{
+		SocketRead,
+		SocketWrite,
+	},
+	TimeoutError,
 };
-use tokio_timer::Timeout;
 
 pub fn main() {
 	env_logger::init();
@@ -25,14 +27,10 @@ pub fn main() {
 		let whandle = handle.clone();
 		buf.resize_with(512, Default::default);
 		tokio_current_thread::spawn(
-			Timeout::new(c.read(&handle, buf).from_err(), Duration::from_secs(3))
+			c.read_timeout(&handle, buf, Duration::from_secs(3))
 			.map_err(|e| {
 				eprintln!("timout/read error");
-				if e.is_inner() {
-					e.into_inner().expect("inner")
-				} else {
-					io::Error::new(io::ErrorKind::TimedOut, "timeout")
-				}
+				io::Error::from(e)
 			})
 			.and_then(move |(n, mut buf, c)| {
 				buf.truncate(n);
@@ -47,11 +45,11 @@ pub fn main() {
 
 	let handle = runtime.reactor_handle();
 	runtime.spawn(
-		Timeout::new(l.incoming(&handle), Duration::from_secs(30))
-		.map_err(|e| {
-			if e.is_inner() {
-				panic!(e.into_inner().expect("inner"));
-			}
+		handle.timeout(l.incoming(&handle), Duration::from_secs(30))
+		.map_err(|e| match e {
+			TimeoutError::Elapsed => (),
+			TimeoutError::Inner(e) => panic!("{}", e),
+			TimeoutError::Timer(e) => panic!("{}", e),
 		})
 		.for_each(connection_handler)
 		.map(|()| eprintln!("listening done"))
@@ -7,18 +7,17 @@ use futures::Future;
 use tokio_current_thread::{self, CurrentThread};
 use tokio_executor::{self, Enter};
 use tokio_uring_reactor::{self, Handle, Reactor};
-use tokio_timer::{self, Timer};
 
 
 pub struct Runtime {
-	executor: CurrentThread<Timer<Reactor>>,
+	executor: CurrentThread<Reactor>,
 }
 
 impl Runtime {
 	/// Create new Runtime
 	pub fn new() -> io::Result<Self> {
 		let reactor = Reactor::new()?;
-		let executor = CurrentThread::new_with_park(Timer::new(reactor));
+		let executor = CurrentThread::new_with_park(reactor);
 
 		Ok(Runtime {
 			executor,
@@ -99,13 +98,19 @@ impl Runtime {
 		}
 	}
 
-	/// Get `Reactor` handle for this `Runtime`
-	pub fn reactor_handle(&mut self) -> Handle {
-		self.executor.get_park().get_park().handle()
+	/// Cancel all active IO operations and wait up to `timeout` for
+	/// them to complete.
+	///
+	/// See `Reactor::shutdown`.
+	pub fn shutdown(&mut self, timeout: Duration) -> io::Result<()> {
+		self.executor.get_park_mut().shutdown(timeout)
 	}
 
-	/// Get `Timer` handle for this `Runtime`
-	pub fn timer_handle(&mut self) -> tokio_timer::timer::Handle {
+	/// Get `Reactor` handle for this `Runtime`
+	///
+	/// Also provides timers (`Handle::sleep`, `Handle::interval` and
+	/// `Handle::timeout`).
+	pub fn reactor_handle(&mut self) -> Handle {
 		self.executor.get_park().handle()
 	}
 }
@@ -173,19 +178,11 @@ impl<'a> Entered<'a> {
 	where
 		F: FnOnce(Borrow) -> T,
 	{
-		let (reactor_handle, timer_handle) = {
-			let timer = self.runtime.executor.get_park();
-			(
-				timer.get_park().handle(),
-				timer.handle(),
-			)
-		};
+		let reactor_handle = self.runtime.executor.get_park().handle();
 		let runtime = &mut self.runtime;
 		with_reactor_handle(&reactor_handle, self.enter, |enter| {
-			tokio_timer::with_default(&timer_handle, enter, |enter| {
-				f(Borrow {
-					executor: runtime.executor.enter(enter),
-				})
+			f(Borrow {
+				executor: runtime.executor.enter(enter),
 			})
 		})
 	}
@@ -276,5 +273,5 @@ impl<'a> fmt::Debug for Entered<'a> {
 }
 
 struct Borrow<'a> {
-	executor: tokio_current_thread::Entered<'a, Timer<Reactor>>,
+	executor: tokio_current_thread::Entered<'a, Reactor>,
 }